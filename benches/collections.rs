@@ -0,0 +1,168 @@
+//! Times the day06 guard walk and compares the std `HashSet`, `FastHashSet` and `PointSet`
+//! on the cells it visits.
+//!
+//! Run with `cargo bench --bench collections`. It uses `assets/input_day06` if present and a
+//! generated map of the same size otherwise.
+
+use aoc2024::collections::{FastHashSet, PointSet};
+use aoc2024::day06::{self, Position, Trajectory};
+use aoc2024::generate::{Generator, GuardMap};
+use std::collections::HashSet;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const INPUT: &str = "assets/input_day06";
+const ITERATIONS: u32 = 200;
+
+trait Cells {
+    fn with_size(width: i32, height: i32) -> Self;
+    fn insert(&mut self, x: i32, y: i32);
+    fn contains(&self, x: i32, y: i32) -> bool;
+    fn len(&self) -> usize;
+}
+
+impl Cells for HashSet<(i32, i32)> {
+    fn with_size(_: i32, _: i32) -> Self {
+        HashSet::new()
+    }
+    fn insert(&mut self, x: i32, y: i32) {
+        HashSet::insert(self, (x, y));
+    }
+    fn contains(&self, x: i32, y: i32) -> bool {
+        HashSet::contains(self, &(x, y))
+    }
+    fn len(&self) -> usize {
+        HashSet::len(self)
+    }
+}
+
+impl Cells for FastHashSet<(i32, i32)> {
+    fn with_size(_: i32, _: i32) -> Self {
+        FastHashSet::default()
+    }
+    fn insert(&mut self, x: i32, y: i32) {
+        HashSet::insert(self, (x, y));
+    }
+    fn contains(&self, x: i32, y: i32) -> bool {
+        HashSet::contains(self, &(x, y))
+    }
+    fn len(&self) -> usize {
+        HashSet::len(self)
+    }
+}
+
+impl Cells for PointSet {
+    fn with_size(width: i32, height: i32) -> Self {
+        PointSet::new(width, height)
+    }
+    fn insert(&mut self, x: i32, y: i32) {
+        PointSet::insert(self, x, y);
+    }
+    fn contains(&self, x: i32, y: i32) -> bool {
+        PointSet::contains(self, x, y)
+    }
+    fn len(&self) -> usize {
+        PointSet::len(self)
+    }
+}
+
+/// The map and the path day06 walks on it.
+struct Walk {
+    width: i32,
+    height: i32,
+    obstacles: Vec<(i32, i32)>,
+    path: Vec<Position>,
+}
+
+fn read_map() -> String {
+    std::fs::read_to_string(INPUT).unwrap_or_else(|_| {
+        println!("{} is missing, using a generated map", INPUT);
+        GuardMap {
+            loop_free: true,
+            ..Default::default()
+        }
+        .generate(2024)
+    })
+}
+
+/// Times day06 walking the guard, which keeps its cells in `PointSet`s.
+fn bench_day06(map: &str) -> Walk {
+    let trajectory = Trajectory::from_map(map).expect("the guard leaves the map");
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let (mut map, guard) = day06::parse_input(black_box(map).lines().map(str::to_string))
+            .expect("the map was read before");
+        day06::move_guard(&mut map, guard, |_, _| Ok(())).expect("the guard leaves the map");
+        black_box(map.visited().len());
+    }
+    println!(
+        "{:<24} {:>12?} per walk",
+        "day06 parse and walk",
+        start.elapsed() / ITERATIONS
+    );
+
+    let lines: Vec<&str> = map.lines().filter(|line| !line.is_empty()).collect();
+    let mut obstacles = vec![];
+    for (y, line) in lines.iter().enumerate() {
+        for (x, char) in line.chars().enumerate() {
+            if char == '#' {
+                obstacles.push((x as i32, y as i32));
+            }
+        }
+    }
+    Walk {
+        width: lines.first().map_or(0, |line| line.chars().count()) as i32,
+        height: lines.len() as i32,
+        obstacles,
+        path: trajectory
+            .steps()
+            .iter()
+            .map(|step| step.position.clone())
+            .collect(),
+    }
+}
+
+/// Does to `S` what day06 does to its sets: fills in the obstacles, then checks the way
+/// ahead and marks the cell visited for every step of the recorded path.
+fn replay<S: Cells>(walk: &Walk) -> usize {
+    let mut obstacles = S::with_size(walk.width, walk.height);
+    for &(x, y) in &walk.obstacles {
+        obstacles.insert(x, y);
+    }
+    let mut visited = S::with_size(walk.width, walk.height);
+    for position in &walk.path {
+        black_box(obstacles.contains(position.x, position.y));
+        visited.insert(position.x, position.y);
+    }
+    visited.len()
+}
+
+fn bench<S: Cells>(name: &str, walk: &Walk) -> Duration {
+    let expected = replay::<S>(walk);
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        assert_eq!(replay::<S>(black_box(walk)), expected);
+    }
+    let per_run = start.elapsed() / ITERATIONS;
+    println!("{:<24} {:>12?} per walk", name, per_run);
+    per_run
+}
+
+fn main() {
+    let walk = bench_day06(&read_map());
+    println!();
+
+    let std = bench::<HashSet<(i32, i32)>>("std HashSet", &walk);
+    let fast = bench::<FastHashSet<(i32, i32)>>("FastHashSet", &walk);
+    let points = bench::<PointSet>("PointSet", &walk);
+
+    println!();
+    println!(
+        "FastHashSet speedup: {:.1}x",
+        std.as_secs_f64() / fast.as_secs_f64()
+    );
+    println!(
+        "PointSet speedup:    {:.1}x",
+        std.as_secs_f64() / points.as_secs_f64()
+    );
+}
@@ -0,0 +1,63 @@
+//! Parses a generated day01 input and computes both parts on it.
+//!
+//! Run with `cargo bench --bench day01`, the number of lines defaults to 10 million and can
+//! be set with `DAY01_BENCH_LINES`.
+
+use aoc2024::day01;
+use std::hint::black_box;
+use std::io::Write;
+use std::time::Instant;
+
+const DEFAULT_LINES: usize = 10_000_000;
+
+/// Lines of two five digit ids like the real input, drawn from a smaller range so the
+/// lists share ids.
+fn generate(lines: usize) -> Vec<u8> {
+    let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+    // xorshift64, good enough to vary the input.
+    let mut random = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        10_000 + state % 90_000
+    };
+
+    let mut input = Vec::with_capacity(lines * 12);
+    for _ in 0..lines {
+        writeln!(input, "{}   {}", random(), random()).expect("writing to a Vec never fails");
+    }
+    input
+}
+
+fn main() {
+    let lines = std::env::var("DAY01_BENCH_LINES")
+        .ok()
+        .and_then(|lines| lines.parse().ok())
+        .unwrap_or(DEFAULT_LINES);
+
+    let start = Instant::now();
+    let input = generate(lines);
+    println!("Generated {} lines in {:?}", lines, start.elapsed());
+
+    let start = Instant::now();
+    let (list_1, list_2) =
+        day01::extract_lists::<u32, _>(input.as_slice()).expect("generated input is valid");
+    let parsing = start.elapsed();
+
+    let start = Instant::now();
+    let distance = day01::calculate_list_distance(&list_1, &list_2);
+    let part_1 = start.elapsed();
+
+    let start = Instant::now();
+    let score = day01::calculate_similarity_score(&list_1, &list_2);
+    let part_2 = start.elapsed();
+    black_box((distance, score));
+
+    println!(
+        "parsing {:?} ({:.1} MiB/s), part 1 {:?}, part 2 {:?}",
+        parsing,
+        input.len() as f64 / 1024.0 / 1024.0 / parsing.as_secs_f64(),
+        part_1,
+        part_2
+    );
+}
@@ -0,0 +1,46 @@
+//! Streams generated corrupted memory through the day03 tokenizer, parser and interpreters.
+//!
+//! Run with `cargo bench --bench day03`, the amount of input defaults to 256 MiB and can be
+//! set with `DAY03_BENCH_MIB`. The input is generated while it is read, so neither side
+//! holds more than a chunk of it in memory.
+
+use aoc2024::day03;
+use aoc2024::generate::{CorruptedMemory, Rng};
+use std::hint::black_box;
+use std::io;
+use std::time::Instant;
+
+const DEFAULT_MIB: u64 = 256;
+const SEED: u64 = 2024;
+
+fn main() {
+    let mib = std::env::var("DAY03_BENCH_MIB")
+        .ok()
+        .and_then(|mib| mib.parse().ok())
+        .unwrap_or(DEFAULT_MIB);
+    let memory = CorruptedMemory {
+        bytes: mib * 1024 * 1024,
+    };
+
+    let start = Instant::now();
+    let mut generated = memory.reader(Rng::new(SEED));
+    io::copy(&mut generated, &mut io::sink()).expect("generating never fails");
+    let generation = start.elapsed();
+
+    let start = Instant::now();
+    let sums =
+        day03::calculate_input(memory.reader(Rng::new(SEED))).expect("generating never fails");
+    let total = start.elapsed();
+    black_box(sums);
+
+    let processing = total.saturating_sub(generation);
+    println!(
+        "{} MiB in {:?} ({:?} of it generating)",
+        mib, total, generation
+    );
+    println!(
+        "{:.1} MiB/s, {:.1} MiB/s without generating",
+        mib as f64 / total.as_secs_f64(),
+        mib as f64 / processing.as_secs_f64()
+    );
+}
@@ -0,0 +1,76 @@
+//! Times parsing and both parts of day06.
+//!
+//! Run with `cargo bench --bench day06`. It uses `assets/input_day06` if present and a
+//! generated map of the same size otherwise. The number of runs defaults to 20 and can be
+//! set with `DAY06_BENCH_RUNS`.
+
+use aoc2024::day06::{self, Trajectory};
+use aoc2024::generate::{Generator, GuardMap};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const INPUT: &str = "assets/input_day06";
+const DEFAULT_RUNS: usize = 20;
+const SEEDS: u64 = 200;
+
+fn median(mut times: Vec<Duration>) -> Duration {
+    times.sort_unstable();
+    times[times.len() / 2]
+}
+
+/// Random maps mostly let the guard out after a few turns, the one with the longest walk
+/// of the first seeds comes closer to a real input.
+fn generated_map() -> String {
+    let generator = GuardMap {
+        loop_free: true,
+        ..Default::default()
+    };
+    let (seed, map, steps) = (0..SEEDS)
+        .map(|seed| {
+            let map = generator.generate(seed);
+            let steps = Trajectory::from_map(&map).map_or(0, |walk| walk.steps().len());
+            (seed, map, steps)
+        })
+        .max_by_key(|(_, _, steps)| *steps)
+        .expect("there is at least one seed");
+    println!(
+        "{} is missing, using the generated map of seed {} with {} steps",
+        INPUT, seed, steps
+    );
+    map
+}
+
+fn main() {
+    let runs = std::env::var("DAY06_BENCH_RUNS")
+        .ok()
+        .and_then(|runs| runs.parse().ok())
+        .unwrap_or(DEFAULT_RUNS)
+        .max(1);
+    let input = std::fs::read_to_string(INPUT).unwrap_or_else(|_| generated_map());
+
+    let (mut parsing, mut part_1, mut part_2) = (vec![], vec![], vec![]);
+    for _ in 0..runs {
+        let start = Instant::now();
+        let (mut map, guard) = day06::parse_input(black_box(&input).lines().map(str::to_string))
+            .expect("the input is a valid map");
+        parsing.push(start.elapsed());
+
+        let start = Instant::now();
+        day06::move_guard(&mut map, guard.clone(), |_, _| Ok(()))
+            .expect("the guard leaves the map");
+        black_box(map.visited().len());
+        part_1.push(start.elapsed());
+
+        let start = Instant::now();
+        black_box(day06::count_loop_obstructions(&map, &guard));
+        part_2.push(start.elapsed());
+    }
+
+    println!(
+        "median of {} runs: parsing {:?}, part 1 {:?}, part 2 {:?}",
+        runs,
+        median(parsing),
+        median(part_1),
+        median(part_2)
+    );
+}
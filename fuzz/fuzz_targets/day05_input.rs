@@ -0,0 +1,6 @@
+#![no_main]
+
+use aoc2024::day05;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| day05::fuzz_input(data));
@@ -0,0 +1,6 @@
+//! Generated from `aoc2024::DAYS`, runs day 13 with the run options of `aoc2024 day`,
+//! e.g. `cargo run --bin day13 -- --explain`.
+
+fn main() -> color_eyre::Result<()> {
+    aoc2024::cli::run_single(13)
+}
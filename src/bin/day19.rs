@@ -0,0 +1,6 @@
+//! Generated from `aoc2024::DAYS`, runs day 19 with the run options of `aoc2024 day`,
+//! e.g. `cargo run --bin day19 -- --explain`.
+
+fn main() -> color_eyre::Result<()> {
+    aoc2024::cli::run_single(19)
+}
@@ -0,0 +1,244 @@
+//! Command line options shared by the `aoc2024` runner and the `dayNN` binaries.
+
+use crate::highlight::HighlightOptions;
+use crate::{run_day, set_run_options, RunOptions};
+use clap::{Args, Parser};
+use color_eyre::Result;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::fmt::format::FmtSpan;
+
+/// Runs a single day with the run options of `aoc2024 day`.
+#[derive(Parser)]
+#[command(about, long_about = None)]
+struct SingleDay {
+    #[command(flatten)]
+    run: RunArgs,
+
+    /// Show debug output, repeat for trace output
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    verbose: u8,
+}
+
+/// Entry point of the `dayNN` binaries: reads the run options from the command line, sets
+/// up error reports and tracing and runs the day.
+pub fn run_single(day: u8) -> Result<()> {
+    let cli = SingleDay::parse();
+    init(cli.verbose)?;
+    set_run_options(cli.run.options());
+    run_day(day)
+}
+
+/// Installs the error report handler and the tracing subscriber.
+pub fn init(verbose: u8) -> Result<()> {
+    color_eyre::install()?;
+    init_tracing(verbose);
+    Ok(())
+}
+
+/// The run options of `aoc2024 day` and the day binaries.
+#[derive(Args)]
+pub struct RunArgs {
+    /// Render simulations in the terminal
+    #[cfg(feature = "viz")]
+    #[arg(long)]
+    visualize: bool,
+
+    /// Frame rate to start visualising at, + and - change it while running
+    #[cfg(feature = "viz")]
+    #[arg(
+        long,
+        requires = "visualize",
+        value_parser = clap::value_parser!(u32).range(1..=i64::from(crate::viz::MAX_FPS))
+    )]
+    fps: Option<u32>,
+
+    /// Write the recorded path of simulations to a text file
+    #[arg(long, value_name = "PATH")]
+    record: Option<std::path::PathBuf>,
+
+    /// Explain what is wrong with rejected puzzle input
+    #[arg(long)]
+    explain: bool,
+
+    /// Write the explanations as JSON to a file
+    #[arg(long, value_name = "PATH")]
+    explain_json: Option<std::path::PathBuf>,
+
+    /// Write the rules of graph puzzles to a Graphviz DOT file
+    #[arg(long, value_name = "PATH")]
+    dot: Option<std::path::PathBuf>,
+
+    /// Highlight the pages of this update (counted from 1) in the DOT file
+    #[arg(long, value_name = "N", requires = "dot")]
+    dot_update: Option<usize>,
+
+    /// Which page is the middle of an update with an even number of pages
+    #[arg(long, value_enum, default_value_t)]
+    even_middle: crate::day05::EvenMiddle,
+
+    #[command(flatten)]
+    highlight: HighlightArgs,
+
+    #[cfg(feature = "export")]
+    #[command(flatten)]
+    export: ExportArgs,
+}
+
+#[derive(Args)]
+struct HighlightArgs {
+    /// Print grid puzzles with the matched cells highlighted
+    #[arg(long)]
+    highlight: bool,
+
+    /// Only show the letters of matches when highlighting
+    #[arg(long)]
+    only_matched: bool,
+
+    /// Write grid puzzles with the matched cells highlighted to an HTML file
+    #[arg(long, value_name = "PATH")]
+    html: Option<std::path::PathBuf>,
+}
+
+#[cfg(feature = "export")]
+#[derive(Args)]
+struct ExportArgs {
+    /// Write simulations to an image, the format follows from the extension (png, gif or svg)
+    #[arg(long, value_name = "PATH")]
+    export: Option<std::path::PathBuf>,
+
+    /// Size of a grid cell in the exported image, in pixels
+    #[arg(long, default_value_t = 4, requires = "export")]
+    cell_size: u16,
+
+    /// Only put every n-th frame into an exported animation
+    #[arg(long, default_value_t = 50, requires = "export")]
+    frame_step: usize,
+
+    /// Comma separated hex colours replacing the default palette, e.g. 1e1e1e,ffcc00
+    #[arg(long, value_name = "COLORS", requires = "export")]
+    palette: Option<String>,
+}
+
+impl RunArgs {
+    // Which fields are set depends on the enabled features.
+    #[allow(clippy::needless_update)]
+    pub fn options(&self) -> RunOptions {
+        RunOptions {
+            record: self.record.clone(),
+            explain: self.explain,
+            explain_json: self.explain_json.clone(),
+            dot: self.dot.clone(),
+            dot_update: self.dot_update,
+            even_middle: self.even_middle,
+            highlight: HighlightOptions {
+                print: self.highlight.highlight,
+                only_matched: self.highlight.only_matched,
+                html: self.highlight.html.clone(),
+            },
+            #[cfg(feature = "viz")]
+            visualize: self.visualize,
+            #[cfg(feature = "viz")]
+            fps: self.fps,
+            #[cfg(feature = "export")]
+            export: self
+                .export
+                .export
+                .as_ref()
+                .map(|path| crate::export::ExportOptions {
+                    path: path.clone(),
+                    cell_size: self.export.cell_size,
+                    frame_step: self.export.frame_step,
+                    palette: self.export.palette.clone(),
+                }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Logs warnings only by default, `-v` adds debug and `-vv` trace output.
+/// Closing day and part spans report their timing from `-v` on.
+fn init_tracing(verbose: u8) {
+    let level = match verbose {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_target(false)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::DAYS;
+    use std::fs;
+    use std::path::Path;
+
+    const BEGIN: &str =
+        "# Day binaries, generated from aoc2024::DAYS by `UPDATE_DAY_BINARIES=1 cargo test day_binaries`.\n";
+    const END: &str = "# End of the day binaries.\n";
+
+    fn wrapper(day: usize) -> String {
+        format!(
+            "//! Generated from `aoc2024::DAYS`, runs day {day} with the run options of `aoc2024 day`,\n\
+             //! e.g. `cargo run --bin day{day:02} -- --explain`.\n\
+             \n\
+             fn main() -> color_eyre::Result<()> {{\n    aoc2024::cli::run_single({day})\n}}\n"
+        )
+    }
+
+    fn bin_entries() -> String {
+        (1..=DAYS.len())
+            .map(|day| {
+                format!(
+                    "[[bin]]\nname = \"day{day:02}\"\npath = \"src/bin/day{day:02}.rs\"\ntest = false\n"
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// The `src/bin/dayNN.rs` wrappers and their `[[bin]]` entries follow `DAYS`, run with
+    /// `UPDATE_DAY_BINARIES=1` to write them after adding a day.
+    #[test]
+    fn day_binaries() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let update = std::env::var_os("UPDATE_DAY_BINARIES").is_some();
+
+        let manifest_path = root.join("Cargo.toml");
+        let manifest = fs::read_to_string(&manifest_path).unwrap();
+        let start = manifest
+            .find(BEGIN)
+            .expect("Cargo.toml marks the day binaries")
+            + BEGIN.len();
+        let end = start
+            + manifest[start..]
+                .find(END)
+                .expect("Cargo.toml ends the day binaries");
+        let entries = bin_entries();
+        if update {
+            let manifest = format!("{}{}{}", &manifest[..start], entries, &manifest[end..]);
+            fs::write(&manifest_path, manifest).unwrap();
+        } else {
+            assert_eq!(
+                manifest[start..end],
+                entries,
+                "run with UPDATE_DAY_BINARIES=1"
+            );
+        }
+
+        for day in 1..=DAYS.len() {
+            let path = root.join(format!("src/bin/day{:02}.rs", day));
+            if update {
+                fs::write(&path, wrapper(day)).unwrap();
+            } else {
+                let source = fs::read_to_string(&path).unwrap_or_default();
+                assert_eq!(source, wrapper(day), "run with UPDATE_DAY_BINARIES=1");
+            }
+        }
+    }
+}
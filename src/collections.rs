@@ -0,0 +1,151 @@
+use std::collections::{HashMap, HashSet};
+
+pub use rustc_hash::FxBuildHasher as FastBuildHasher;
+
+/// `HashMap` with a fast, non-cryptographic hasher. Create it with `FastHashMap::default()`.
+pub type FastHashMap<K, V> = HashMap<K, V, FastBuildHasher>;
+
+/// `HashSet` with a fast, non-cryptographic hasher. Create it with `FastHashSet::default()`.
+pub type FastHashSet<T> = HashSet<T, FastBuildHasher>;
+
+/// Set of points on a bounded `width` x `height` grid, stored as one bit per cell.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PointSet {
+    width: i32,
+    height: i32,
+    bits: Vec<u64>,
+    len: usize,
+}
+
+impl PointSet {
+    pub fn new(width: i32, height: i32) -> Self {
+        assert!(
+            width >= 0 && height >= 0,
+            "PointSet needs a non negative size"
+        );
+        let cells = width as usize * height as usize;
+
+        Self {
+            width,
+            height,
+            bits: vec![0; cells.div_ceil(64)],
+            len: 0,
+        }
+    }
+
+    pub fn width(&self) -> i32 {
+        self.width
+    }
+
+    pub fn height(&self) -> i32 {
+        self.height
+    }
+
+    pub fn in_bounds(&self, x: i32, y: i32) -> bool {
+        x >= 0 && x < self.width && y >= 0 && y < self.height
+    }
+
+    /// Adds the point, returning whether it was newly inserted.
+    ///
+    /// Panics if the point lies outside of the grid.
+    pub fn insert(&mut self, x: i32, y: i32) -> bool {
+        let (word, mask) = self
+            .locate(x, y)
+            .unwrap_or_else(|| panic!("Tried to insert ({}, {}) outside of PointSet", x, y));
+
+        let newly_inserted = self.bits[word] & mask == 0;
+        if newly_inserted {
+            self.bits[word] |= mask;
+            self.len += 1;
+        }
+        newly_inserted
+    }
+
+    /// Removes the point, returning whether it was present.
+    pub fn remove(&mut self, x: i32, y: i32) -> bool {
+        let Some((word, mask)) = self.locate(x, y) else {
+            return false;
+        };
+
+        let was_present = self.bits[word] & mask != 0;
+        if was_present {
+            self.bits[word] &= !mask;
+            self.len -= 1;
+        }
+        was_present
+    }
+
+    /// Whether the point is in the set. Points outside of the grid never are.
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        self.locate(x, y)
+            .map(|(word, mask)| self.bits[word] & mask != 0)
+            .unwrap_or(false)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn clear(&mut self) {
+        self.bits.fill(0);
+        self.len = 0;
+    }
+
+    /// Iterates over the points in row-major order.
+    pub fn iter(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.bits.iter().enumerate().flat_map(move |(word, &bits)| {
+            (0..64)
+                .filter(move |bit| bits & (1 << bit) != 0)
+                .map(move |bit| {
+                    let index = (word * 64 + bit) as i32;
+                    (index % self.width, index / self.width)
+                })
+        })
+    }
+
+    fn locate(&self, x: i32, y: i32) -> Option<(usize, u64)> {
+        if !self.in_bounds(x, y) {
+            return None;
+        }
+        let index = y as usize * self.width as usize + x as usize;
+        Some((index / 64, 1 << (index % 64)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn point_set_membership() {
+        let mut set = PointSet::new(10, 7);
+
+        assert!(set.insert(0, 0));
+        assert!(set.insert(9, 6));
+        assert!(set.insert(3, 4));
+        assert!(!set.insert(3, 4));
+
+        assert_eq!(set.len(), 3);
+        assert!(set.contains(9, 6));
+        assert!(!set.contains(4, 3));
+        assert!(!set.contains(-1, 0));
+        assert!(!set.contains(10, 0));
+
+        assert!(set.remove(0, 0));
+        assert!(!set.remove(0, 0));
+        assert_eq!(set.iter().collect::<Vec<_>>(), vec![(3, 4), (9, 6)]);
+
+        set.clear();
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn point_set_insert_out_of_bounds() {
+        PointSet::new(3, 3).insert(3, 0);
+    }
+}
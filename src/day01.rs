@@ -1,17 +1,36 @@
-use aoc2024::{count_occurrences, print_day_title, print_part_solution, read_lines};
-use color_eyre::Result;
+use crate::collections::FastHashMap;
+use crate::fuzzing::deterministic;
+use crate::{print_day_title, print_part_solution};
+use color_eyre::{eyre::eyre, Result};
+use std::fmt;
+use std::fs::File;
+use std::hash::Hash;
+use std::io::{BufRead, BufReader};
+use std::marker::PhantomData;
+use std::str::FromStr;
+use tracing::info_span;
 
 const INPUT: &str = "assets/input_day01";
+/// Malformed lines shown in the error before the rest are only counted.
+const SHOWN_MALFORMED: usize = 10;
+
+/// Integer ids the lists can hold, widened to `i128` for distances and scores.
+pub trait Id: Copy + Ord + Hash + FromStr + Into<i128> {}
+
+impl<T: Copy + Ord + Hash + FromStr + Into<i128>> Id for T {}
 
 pub fn main() -> Result<()> {
     print_day_title(1);
 
-    let (list_1, list_2) = extract_lists(INPUT);
+    let (list_1, list_2) = read_lists::<u32>(INPUT)?;
 
-    let distance = calculate_list_distance(&list_1, &list_2).unwrap();
+    let distance = info_span!("part", part = 1)
+        .in_scope(|| calculate_list_distance(&list_1, &list_2))
+        .ok_or_else(|| eyre!("The lists have different lengths"))?;
     print_part_solution(1, "The distance between the lists is:", distance);
 
-    let similiarity_score = calculate_similarity_score(&list_1, &list_2);
+    let similiarity_score =
+        info_span!("part", part = 2).in_scope(|| calculate_similarity_score(&list_1, &list_2));
     print_part_solution(
         2,
         "The similiarity score of the lists is:",
@@ -20,7 +39,7 @@ pub fn main() -> Result<()> {
     Ok(())
 }
 
-fn calculate_list_distance(list_1: &[i32], list_2: &[i32]) -> Option<i32> {
+pub fn calculate_list_distance<T: Id>(list_1: &[T], list_2: &[T]) -> Option<i128> {
     if list_1.len() != list_2.len() {
         return None;
     }
@@ -31,46 +50,175 @@ fn calculate_list_distance(list_1: &[i32], list_2: &[i32]) -> Option<i32> {
 
     Some(
         sorted_list_1
-            .iter()
-            .zip(sorted_list_2.iter())
-            .map(|(a, b)| (b - a).abs())
+            .into_iter()
+            .zip(sorted_list_2)
+            .map(|(a, b)| (b.into() - a.into()).abs())
             .sum(),
     )
 }
 
-fn calculate_similarity_score(list_1: &[i32], list_2: &[i32]) -> usize {
-    let mut score: usize = 0;
+/// Sums every id of the first list times how often it is in the second, counting the
+/// second list once up front.
+pub fn calculate_similarity_score<T: Id>(list_1: &[T], list_2: &[T]) -> i128 {
+    let mut occurrences: FastHashMap<T, i128> = FastHashMap::default();
+    for &id in list_2 {
+        *occurrences.entry(id).or_default() += 1;
+    }
 
     list_1
         .iter()
-        .cloned()
-        .for_each(|it| score += it as usize * count_occurrences(list_2, it));
+        .filter_map(|id| Some((*id).into() * occurrences.get(id)?))
+        .sum()
+}
+
+/// A line that isn't two ids separated by whitespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Malformed {
+    /// Counted from 1.
+    pub line: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for Malformed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.reason)
+    }
+}
+
+/// The pairs of ids in a reader, one line at a time. Empty lines are skipped, a read
+/// error ends the pairs after being reported.
+pub struct Pairs<R, T> {
+    reader: R,
+    buffer: String,
+    line: usize,
+    failed: bool,
+    ids: PhantomData<T>,
+}
+
+impl<R: BufRead, T> Pairs<R, T> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buffer: String::new(),
+            line: 0,
+            failed: false,
+            ids: PhantomData,
+        }
+    }
+
+    fn pair(&self) -> Result<(T, T), String>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        let mut fields = self.buffer.split_whitespace();
+        let mut id = || {
+            let field = fields.next().ok_or("expected two ids")?;
+            field
+                .parse()
+                .map_err(|error| format!("bad id {:?}: {}", field, error))
+        };
+        let pair = (id()?, id()?);
 
-    score
+        match fields.next() {
+            Some(_) => Err("expected two ids, found more".to_string()),
+            None => Ok(pair),
+        }
+    }
+}
+
+impl<R: BufRead, T: FromStr> Iterator for Pairs<R, T>
+where
+    T::Err: fmt::Display,
+{
+    type Item = Result<(T, T), Malformed>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.failed {
+            self.buffer.clear();
+            self.line += 1;
+            let malformed = |reason| Malformed {
+                line: self.line,
+                reason,
+            };
+
+            match self.reader.read_line(&mut self.buffer) {
+                Ok(0) => return None,
+                Ok(_) if self.buffer.trim().is_empty() => continue,
+                Ok(_) => return Some(self.pair().map_err(malformed)),
+                Err(error) => {
+                    self.failed = true;
+                    return Some(Err(malformed(format!("failed to read: {}", error))));
+                }
+            }
+        }
+        None
+    }
 }
 
-fn extract_lists(file: &str) -> (Vec<i32>, Vec<i32>) {
-    let lines = read_lines(file).unwrap();
-
-    let mut list_1: Vec<i32> = Vec::default();
-    let mut list_2: Vec<i32> = Vec::default();
-
-    lines.map_while(Result::ok).for_each(|line| {
-        let ids: Vec<i32> = line
-            .split(" ")
-            .map(|id| id.trim().parse())
-            .filter_map(Result::ok)
-            .collect();
-        assert_eq!(ids.len(), 2);
-        list_1.push(ids[0]);
-        list_2.push(ids[1]);
+/// Both lists of a reader, or an error listing the malformed lines.
+pub fn extract_lists<T, R>(reader: R) -> Result<(Vec<T>, Vec<T>)>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+    R: BufRead,
+{
+    let mut list_1 = vec![];
+    let mut list_2 = vec![];
+    let mut malformed = vec![];
+
+    for pair in Pairs::new(reader) {
+        match pair {
+            Ok((id_1, id_2)) => {
+                list_1.push(id_1);
+                list_2.push(id_2);
+            }
+            Err(line) => malformed.push(line),
+        }
+    }
+
+    if malformed.is_empty() {
+        return Ok((list_1, list_2));
+    }
+
+    let mut message = format!("{} malformed line(s)", malformed.len());
+    for line in malformed.iter().take(SHOWN_MALFORMED) {
+        message.push_str(&format!("\n  {}", line));
+    }
+    if malformed.len() > SHOWN_MALFORMED {
+        message.push_str("\n  ...");
+    }
+    Err(eyre!(message))
+}
+
+fn read_lists<T>(file: &str) -> Result<(Vec<T>, Vec<T>)>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    extract_lists(BufReader::new(File::open(file)?))
+}
+
+/// Fuzz target: any input either gives the same lists every time or reports malformed
+/// lines, and the lists it gives can be compared.
+pub fn fuzz_input(data: &[u8]) {
+    let lists = deterministic(data, |data| {
+        extract_lists::<u32, _>(data).map_err(|error| error.to_string())
     });
-    (list_1, list_2)
+    if let Ok((list_1, list_2)) = lists {
+        calculate_list_distance(&list_1, &list_2);
+        calculate_similarity_score(&list_1, &list_2);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fuzzing::run_bounded;
+    use crate::generate::{Generator, IdLists};
+    use crate::{count_occurrences, read_lines};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
     const LIST_1: [i32; 6] = [3, 4, 2, 1, 3, 3];
     const LIST_2: [i32; 6] = [4, 3, 5, 3, 9, 3];
 
@@ -99,9 +247,58 @@ mod tests {
         assert_eq!(occurrence_4, 1);
     }
 
+    #[test]
+    fn integer_types() {
+        let list_1: Vec<u8> = LIST_1.iter().map(|&id| id as u8).collect();
+        let list_2: Vec<u8> = LIST_2.iter().map(|&id| id as u8).collect();
+        assert_eq!(calculate_list_distance(&list_1, &list_2), Some(11));
+        assert_eq!(calculate_similarity_score(&list_1, &list_2), 31);
+
+        let big = [u64::MAX, 0];
+        assert_eq!(
+            calculate_list_distance(&big, &[0, 0]),
+            Some(u64::MAX as i128)
+        );
+        assert_eq!(calculate_similarity_score(&big, &big), u64::MAX as i128);
+    }
+
+    #[test]
+    fn parse_example() {
+        let text = "3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n";
+        let (list_1, list_2) = extract_lists::<i32, _>(text.as_bytes()).unwrap();
+        assert_eq!(list_1, LIST_1);
+        assert_eq!(list_2, LIST_2);
+    }
+
+    #[test]
+    fn malformed_lines() {
+        let text = "1 2\n\n3\n4 five\n-6 7\n8 9 10\n11\t12";
+        let pairs: Vec<_> = Pairs::<_, u32>::new(text.as_bytes()).collect();
+        assert_eq!(pairs[0], Ok((1, 2)));
+        assert_eq!(
+            pairs[1],
+            Err(Malformed {
+                line: 3,
+                reason: "expected two ids".to_string()
+            })
+        );
+        assert_eq!(pairs[2].as_ref().unwrap_err().line, 4);
+        assert!(pairs[3].as_ref().unwrap_err().reason.contains("\"-6\""));
+        assert_eq!(
+            pairs[4].as_ref().unwrap_err().reason,
+            "expected two ids, found more"
+        );
+        assert_eq!(pairs[5], Ok((11, 12)));
+
+        let error = extract_lists::<u32, _>(text.as_bytes()).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("4 malformed line(s)\n  line 3:"));
+    }
+
     #[test]
     fn task_1() {
-        let (list_1, list_2) = extract_lists(INPUT);
+        let (list_1, list_2) = read_lists::<u32>(INPUT).unwrap();
 
         let distance = calculate_list_distance(&list_1, &list_2).unwrap();
         assert_eq!(distance, 765748)
@@ -113,11 +310,75 @@ mod tests {
 
         let mut by_line = String::default();
 
-        for line in read_lines(INPUT).unwrap().flatten() {
+        for line in read_lines(INPUT).unwrap().map_while(Result::ok) {
             by_line.push_str(&line);
             by_line.push('\n');
         }
 
         assert_eq!(file_content, by_line);
     }
+
+    /// Pairs up the smallest remaining ids, one at a time.
+    fn naive_distance(list_1: &[i32], list_2: &[i32]) -> i128 {
+        let mut list_1 = list_1.to_vec();
+        let mut list_2 = list_2.to_vec();
+        let mut distance = 0;
+        while !list_1.is_empty() {
+            let smallest = |list: &[i32]| (0..list.len()).min_by_key(|&i| list[i]).unwrap();
+            let a = list_1.remove(smallest(&list_1));
+            let b = list_2.remove(smallest(&list_2));
+            distance += (a as i128 - b as i128).abs();
+        }
+        distance
+    }
+
+    /// Counts the second list again for every id of the first.
+    fn naive_similarity(list_1: &[i32], list_2: &[i32]) -> i128 {
+        list_1
+            .iter()
+            .map(|&id| id as i128 * count_occurrences(list_2, id) as i128)
+            .sum()
+    }
+
+    #[test]
+    fn fuzz_input_bounded() {
+        let corpus = [
+            b"3   4\n4   3\n2   5\n1   3\n3   9\n3   3\n".to_vec(),
+            IdLists {
+                lines: 20,
+                distinct_ids: 10,
+            }
+            .generate(1)
+            .into_bytes(),
+        ];
+        run_bounded(&corpus, fuzz_input);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn matches_naive(pairs in vec((-50..50, -50..50), 0..60)) {
+            let (list_1, list_2): (Vec<i32>, Vec<i32>) = pairs.into_iter().unzip();
+            prop_assert_eq!(
+                calculate_list_distance(&list_1, &list_2),
+                Some(naive_distance(&list_1, &list_2))
+            );
+            prop_assert_eq!(
+                calculate_similarity_score(&list_1, &list_2),
+                naive_similarity(&list_1, &list_2)
+            );
+        }
+
+        #[test]
+        fn parses_what_it_reads(pairs in vec((0..100_000u32, 0..100_000u32), 0..60)) {
+            let text: String = pairs
+                .iter()
+                .map(|(a, b)| format!("{}   {}\n", a, b))
+                .collect();
+            let (list_1, list_2) = extract_lists::<u32, _>(text.as_bytes()).unwrap();
+            prop_assert_eq!(list_1, pairs.iter().map(|&(a, _)| a).collect::<Vec<_>>());
+            prop_assert_eq!(list_2, pairs.iter().map(|&(_, b)| b).collect::<Vec<_>>());
+        }
+    }
 }
@@ -1,131 +1,339 @@
-use aoc2024::{print_day_title, print_part_solution, read_lines};
-use color_eyre::Result;
+use crate::fuzzing::deterministic;
+use crate::{print_day_title, print_part_solution, read_lines, run_options};
+use color_eyre::{eyre::eyre, Result};
+use std::cmp::Ordering;
+use std::fmt;
+use tracing::info_span;
 
 const INPUT: &str = "assets/input_day02";
 
-#[derive(PartialEq)]
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     Increasing,
     Decreasing,
-    Invalid,
 }
 
-pub fn main() -> Result<()> {
-    print_day_title(2);
-    let result = check_all_reports(INPUT)?;
-    print_part_solution(1, "The amount of safe reports is:", 202);
-    print_part_solution(2, "The amount of safe reports is:", result);
-
-    Ok(())
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Increasing => write!(f, "increasing"),
+            Direction::Decreasing => write!(f, "decreasing"),
+        }
+    }
 }
 
-fn check_all_reports(file: &str) -> Result<i32> {
-    let lines = read_lines(file)?;
+/// The first reason a report is unsafe. `index` is the position of the level a bad
+/// step starts at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Problem {
+    TooShort { levels: usize },
+    StepTooSmall { index: usize, step: i64, min: i32 },
+    StepTooLarge { index: usize, step: i64, max: i32 },
+    WrongDirection { index: usize, expected: Direction },
+}
 
-    let mut safe = 0;
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Problem::TooShort { levels } => write!(f, "has only {} level(s)", levels),
+            Problem::StepTooSmall { index, step, min } => write!(
+                f,
+                "step of {} between positions {} and {} is smaller than {}",
+                step,
+                index,
+                index + 1,
+                min
+            ),
+            Problem::StepTooLarge { index, step, max } => write!(
+                f,
+                "step of {} between positions {} and {} is larger than {}",
+                step,
+                index,
+                index + 1,
+                max
+            ),
+            Problem::WrongDirection { index, expected } => write!(
+                f,
+                "step between positions {} and {} is not {}",
+                index,
+                index + 1,
+                expected
+            ),
+        }
+    }
+}
 
-    lines.map_while(Result::ok).for_each(|line| {
-        let report: Vec<i32> = line
-            .split(" ")
-            .filter(|part| !part.trim().is_empty())
-            .map(|part| part.parse::<i32>().expect("failed to parse report"))
-            .collect();
+/// Decides whether a report is safe: all steps between neighbouring levels go in the same
+/// direction and lie within `min_step..=max_step`, after removing up to `removals` levels.
+///
+/// ```
+/// use aoc2024::day02::{Direction, ReportValidator};
+///
+/// let validator = ReportValidator::default().steps(1, 2).removals(1);
+/// assert_eq!(validator.validate(&[1, 3, 2, 4]), Some(vec![1]));
+/// assert_eq!(validator.direction(Direction::Decreasing).validate(&[1, 3, 2, 4]), None);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReportValidator {
+    min_step: i32,
+    max_step: i32,
+    /// `None` accepts either direction.
+    direction: Option<Direction>,
+    removals: usize,
+}
 
-        if check_report(&report, 0) {
-            safe += 1;
+impl Default for ReportValidator {
+    fn default() -> Self {
+        Self {
+            min_step: 1,
+            max_step: 3,
+            direction: None,
+            removals: 0,
         }
-    });
-
-    Ok(safe)
+    }
 }
 
-fn check_report(report: &[i32], elements_removed: i8) -> bool {
-    if report.len() < 2 {
-        return false;
+impl ReportValidator {
+    pub fn steps(mut self, min_step: i32, max_step: i32) -> Self {
+        self.min_step = min_step;
+        self.max_step = max_step;
+        self
     }
 
-    let mut bad_transition: Option<(usize, usize)> = None;
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
 
-    let direction = get_direction(report);
-    if direction == Direction::Invalid {
-        return false;
+    pub fn removals(mut self, removals: usize) -> Self {
+        self.removals = removals;
+        self
     }
 
-    for (i, pair) in report.windows(2).enumerate() {
-        if let [a, b] = pair {
-            let mut valid_transition = true;
+    /// The indices to remove to make the report safe, as few as possible, or `None` if
+    /// it can't be made safe. A report needs at least two levels left to be safe.
+    pub fn validate(&self, report: &[i32]) -> Option<Vec<usize>> {
+        let directions = match self.direction {
+            Some(direction) => vec![direction],
+            None => vec![Direction::Increasing, Direction::Decreasing],
+        };
+
+        directions
+            .into_iter()
+            .filter_map(|direction| self.fewest_removals(report, direction))
+            .min_by_key(Vec::len)
+    }
 
-            if a == b {
-                valid_transition = false;
-            }
-            if (a - b).abs() > 3 {
-                valid_transition = false;
-            }
+    /// Why the report is unsafe as it is, without removing levels. Without a required
+    /// direction the first step that isn't flat sets it.
+    fn problem(&self, report: &[i32]) -> Option<Problem> {
+        if report.len() < 2 {
+            return Some(Problem::TooShort {
+                levels: report.len(),
+            });
+        }
 
-            if direction == Direction::Increasing && a > b {
-                valid_transition = false;
+        let mut direction = self.direction;
+        for (index, pair) in report.windows(2).enumerate() {
+            let step = pair[1] as i64 - pair[0] as i64;
+            let going = match step.cmp(&0) {
+                Ordering::Greater => Some(Direction::Increasing),
+                Ordering::Less => Some(Direction::Decreasing),
+                Ordering::Equal => None,
+            };
+            if let (Some(expected), Some(going)) = (direction, going) {
+                if going != expected {
+                    return Some(Problem::WrongDirection { index, expected });
+                }
             }
-            if direction == Direction::Decreasing && a < b {
-                valid_transition = false;
+            direction = direction.or(going);
+
+            let step = step.abs();
+            if step < self.min_step as i64 {
+                return Some(Problem::StepTooSmall {
+                    index,
+                    step,
+                    min: self.min_step,
+                });
             }
+            if step > self.max_step as i64 {
+                return Some(Problem::StepTooLarge {
+                    index,
+                    step,
+                    max: self.max_step,
+                });
+            }
+        }
 
-            if !valid_transition {
-                if elements_removed > 0 {
-                    return false;
+        None
+    }
+
+    fn valid_step(&self, from: i32, to: i32, direction: Direction) -> bool {
+        let step = match direction {
+            Direction::Increasing => to as i64 - from as i64,
+            Direction::Decreasing => from as i64 - to as i64,
+        };
+        (self.min_step as i64..=self.max_step as i64).contains(&step)
+    }
+
+    /// For every level, the fewest removals that leave a safe run of at least two levels
+    /// ending in it. The level before it in the run is at most `removals` levels back,
+    /// and everything in between is removed, so this takes O(n·k) for k removals.
+    fn fewest_removals(&self, report: &[i32], direction: Direction) -> Option<Vec<usize>> {
+        let n = report.len();
+        // (removals, previous level in the run) for runs of at least two levels.
+        let mut best: Vec<Option<(usize, usize)>> = vec![None; n];
+
+        for end in 1..n {
+            for gap in 0..=self.removals.min(end - 1) {
+                let previous = end - 1 - gap;
+                if !self.valid_step(report[previous], report[end], direction) {
+                    continue;
+                }
+
+                // The run either starts at `previous` or continues one ending there.
+                let before = match best[previous] {
+                    Some((removed, _)) => removed.min(previous),
+                    None => previous,
+                };
+                let removed = before + gap;
+                if removed <= self.removals && best[end].is_none_or(|(other, _)| removed < other) {
+                    best[end] = Some((removed, previous));
                 }
-                bad_transition = Some((i, i + 1));
+            }
+        }
+
+        let (end, _) = (0..n)
+            .filter_map(|end| Some((end, best[end]?.0 + n - 1 - end)))
+            .filter(|&(_, removed)| removed <= self.removals)
+            .min_by_key(|&(_, removed)| removed)?;
+
+        let mut kept = vec![false; n];
+        let mut current = end;
+        kept[current] = true;
+        while let Some((_, previous)) = best[current] {
+            kept[previous] = true;
+            // The run started at `previous` if that removed no more than continuing one.
+            if best[previous].is_none_or(|(removed, _)| previous <= removed) {
                 break;
             }
+            current = previous;
         }
-    }
 
-    if let Some(bad_transition) = bad_transition {
-        check_without_bad_transition(report, bad_transition)
-    } else {
-        true
+        Some((0..n).filter(|&i| !kept[i]).collect())
     }
 }
 
-fn check_without_bad_transition(report: &[i32], index: (usize, usize)) -> bool {
-    let (a, b) = index;
+pub fn main() -> Result<()> {
+    print_day_title(2);
+    let reports = read_reports(INPUT)?;
 
-    let index_one_removed = check_without_element(report, a);
-    let index_two_removed = check_without_element(report, b);
+    let strict = ReportValidator::default();
+    let tolerant = strict.removals(1);
 
-    index_one_removed || index_two_removed
+    // Part 1
+    let safe = info_span!("part", part = 1).in_scope(|| count_safe(&reports, &strict));
+    print_part_solution(1, "The amount of safe reports is:", safe);
+
+    // Part 2
+    let safe = info_span!("part", part = 2).in_scope(|| count_safe(&reports, &tolerant));
+    print_part_solution(2, "The amount of safe reports is:", safe);
+
+    if run_options().explain {
+        for (index, report) in reports.iter().enumerate() {
+            if let Some(explanation) = explain(index, report, &strict, &tolerant) {
+                println!();
+                print!("{}", explanation);
+            }
+        }
+    }
+
+    Ok(())
 }
 
-fn check_without_element(report: &[i32], index: usize) -> bool {
-    let mut report_without_level = report.to_vec();
+fn read_reports(file: &str) -> Result<Vec<Vec<i32>>> {
+    parse_reports(read_lines(file)?.map_while(Result::ok))
+}
 
-    report_without_level.remove(index);
+fn parse_reports(lines: impl Iterator<Item = String>) -> Result<Vec<Vec<i32>>> {
+    lines
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            line.split_whitespace()
+                .map(|level| {
+                    level.parse().map_err(|error| {
+                        eyre!("Bad level {:?} on line {}: {}", level, index + 1, error)
+                    })
+                })
+                .collect()
+        })
+        .collect()
+}
 
-    check_report(&report_without_level, 1)
+fn count_safe(reports: &[Vec<i32>], validator: &ReportValidator) -> usize {
+    reports
+        .iter()
+        .filter(|report| validator.validate(report).is_some())
+        .count()
 }
 
-fn get_direction(report: &[i32]) -> Direction {
-    let mut asc = 0;
-    let mut desc = 0;
-    let mut eq = 0;
-    for pair in report.windows(2) {
-        if let [a, b] = pair {
-            match a.cmp(b) {
-                std::cmp::Ordering::Greater => desc += 1,
-                std::cmp::Ordering::Less => asc += 1,
-                std::cmp::Ordering::Equal => eq += 1,
-            }
+/// Why a report fails the strict check and whether removing levels saves it, or `None`
+/// for safe reports.
+fn explain(
+    index: usize,
+    report: &[i32],
+    strict: &ReportValidator,
+    tolerant: &ReportValidator,
+) -> Option<String> {
+    let problem = strict.problem(report)?;
+    let levels: Vec<String> = report.iter().map(i32::to_string).collect();
+
+    let mut explanation = format!(
+        "Report {}: {}\n  - {}\n",
+        index + 1,
+        levels.join(" "),
+        problem
+    );
+    match tolerant.validate(report) {
+        Some(removed) => {
+            let positions: Vec<String> = removed.iter().map(usize::to_string).collect();
+            explanation.push_str(&format!(
+                "  - safe after removing position {}\n",
+                positions.join(", ")
+            ));
         }
+        None => explanation.push_str(&format!(
+            "  - still unsafe after removing {} level(s)\n",
+            tolerant.removals
+        )),
     }
+    Some(explanation)
+}
 
-    if eq > 1 || asc > 1 && desc > 1 {
-        Direction::Invalid
-    } else if asc > desc {
-        Direction::Increasing
-    } else if desc > asc {
-        Direction::Decreasing
-    } else {
-        // what if 3 elements - not in inpuut!
-        Direction::Invalid
+/// Fuzz target: any input either gives the same reports every time or a parse error,
+/// every report can be explained and fixing the direction never needs fewer removals.
+pub fn fuzz_input(data: &[u8]) {
+    let reports = deterministic(data, |data| {
+        let lines = String::from_utf8_lossy(data)
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>();
+        parse_reports(lines.into_iter()).map_err(|error| error.to_string())
+    });
+
+    let strict = ReportValidator::default();
+    let tolerant = strict.removals(2);
+    let wide = tolerant.steps(0, 5);
+    for (index, report) in reports.iter().flatten().enumerate() {
+        explain(index, report, &strict, &tolerant);
+
+        let removals = wide.validate(report).map(|removed| removed.len());
+        for direction in [Direction::Increasing, Direction::Decreasing] {
+            if let Some(directed) = wide.direction(direction).validate(report) {
+                assert!(removals.is_some_and(|removals| removals <= directed.len()));
+            }
+        }
     }
 }
 
@@ -140,12 +348,23 @@ mod test {
 
     const LISTS: [[i32; 5]; 6] = [LIST_1, LIST_2, LIST_3, LIST_4, LIST_5, LIST_6];
     use super::*;
+    use crate::fuzzing::run_bounded;
+    use crate::generate::{Generator, Reports};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    fn tolerant(report: &[i32]) -> bool {
+        ReportValidator::default()
+            .removals(1)
+            .validate(report)
+            .is_some()
+    }
 
     #[test]
     fn test_all() {
         let mut safe = 0;
         for list in LISTS {
-            if check_report(&list, 0) {
+            if tolerant(&list) {
                 safe += 1
             }
         }
@@ -155,87 +374,314 @@ mod test {
     #[test]
     fn test_1() {
         let list = [1, 2, 3, 4, 5];
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_2() {
         let list = [1, 1, 3, 4, 5];
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_3() {
         let list = [1, 1, 1, 4, 5];
-        assert!(!check_report(&list, 0));
+        assert!(!tolerant(&list));
     }
 
     #[test]
     fn test_4() {
         let list = [1, 2, 2, 4, 5];
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_5() {
         let list = [1, 2, 2, 4, 3];
-        assert!(!check_report(&list, 0));
+        assert!(!tolerant(&list));
     }
 
     #[test]
     fn test_6() {
         let list = []; // No levels
-        assert!(!check_report(&list, 0));
+        assert!(!tolerant(&list));
     }
 
     #[test]
     fn test_7() {
         let list = [1]; // Single level
-        assert!(!check_report(&list, 0));
+        assert!(!tolerant(&list));
     }
 
     #[test]
     fn test_8() {
         let list = [1, 2, 2, 3]; // Removing either "2" could make it safe
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_9() {
         let list = [1, 5, 6, 9]; // Invalid due to 1 -> 5
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_10() {
         let list = [1, 2, 3, 2, 5]; // Removing "3" or "2" might make it safe
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_11() {
         let list = [9, 8, 6, 6, 5]; // Removing one "6" might make it safe
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_12() {
         let list = [1, 3, 2, 4, 5]; // Removing "2" might make it safe
-        assert!(check_report(&list, 0));
+        assert!(tolerant(&list));
     }
 
     #[test]
     fn test_report() {
-        assert!(check_report(&LIST_1, 0));
-        assert!(!check_report(&LIST_2, 0));
-        assert!(!check_report(&LIST_3, 0));
-        assert!(check_report(&LIST_4, 0));
-        assert!(check_report(&LIST_5, 0));
-        assert!(check_report(&LIST_6, 0));
+        assert!(tolerant(&LIST_1));
+        assert!(!tolerant(&LIST_2));
+        assert!(!tolerant(&LIST_3));
+        assert!(tolerant(&LIST_4));
+        assert!(tolerant(&LIST_5));
+        assert!(tolerant(&LIST_6));
+    }
+
+    #[test]
+    fn removed_indices() {
+        let validator = ReportValidator::default().removals(1);
+        assert_eq!(validator.validate(&LIST_1), Some(vec![]));
+        assert_eq!(validator.validate(&LIST_4), Some(vec![1]));
+        assert_eq!(validator.validate(&LIST_5), Some(vec![2]));
+        assert_eq!(validator.validate(&[1, 5, 6, 9]), Some(vec![0]));
+        assert_eq!(validator.validate(&[1, 2, 3, 4, 9]), Some(vec![4]));
+        assert_eq!(validator.validate(&LIST_2), None);
+    }
+
+    #[test]
+    fn several_removals() {
+        let report = [10, 1, 2, 20, 3, 4, 30];
+        assert_eq!(
+            ReportValidator::default().removals(2).validate(&report),
+            None
+        );
+        assert_eq!(
+            ReportValidator::default().removals(3).validate(&report),
+            Some(vec![0, 3, 6])
+        );
+        // Removals at the start and end of a report are no different from the middle.
+        assert_eq!(
+            ReportValidator::default()
+                .removals(3)
+                .validate(&[9, 9, 9, 1, 2]),
+            Some(vec![0, 1, 2])
+        );
+    }
+
+    #[test]
+    fn bounds_and_direction() {
+        let report = [1, 1, 5, 9, 9];
+        assert_eq!(ReportValidator::default().validate(&report), None);
+        assert_eq!(
+            ReportValidator::default().steps(0, 4).validate(&report),
+            Some(vec![])
+        );
+        assert_eq!(
+            ReportValidator::default()
+                .steps(0, 4)
+                .direction(Direction::Decreasing)
+                .validate(&report),
+            None
+        );
+        assert_eq!(
+            ReportValidator::default()
+                .direction(Direction::Decreasing)
+                .removals(1)
+                .validate(&LIST_4),
+            None
+        );
+        assert_eq!(
+            ReportValidator::default()
+                .direction(Direction::Increasing)
+                .removals(1)
+                .validate(&LIST_4),
+            Some(vec![1])
+        );
+    }
+
+    const EXAMPLE: &str = "\
+7 6 4 2 1
+1 2 7 8 9
+9 7 6 2 1
+1 3 2 4 5
+8 6 4 4 1
+1 3 6 7 9";
+
+    fn example() -> Vec<Vec<i32>> {
+        parse_reports(EXAMPLE.lines().map(String::from)).unwrap()
+    }
+
+    #[test]
+    fn example_parts() {
+        let reports = example();
+        assert_eq!(reports, LISTS.map(Vec::from));
+
+        let strict = ReportValidator::default();
+        assert_eq!(count_safe(&reports, &strict), 2);
+        assert_eq!(count_safe(&reports, &strict.removals(1)), 4);
+    }
+
+    #[test]
+    fn bad_levels() {
+        let error = parse_reports(["1 2 3", "4 x 6"].into_iter().map(String::from)).unwrap_err();
+        assert!(error.to_string().contains("line 2"));
+    }
+
+    #[test]
+    fn problems() {
+        let strict = ReportValidator::default();
+        assert_eq!(strict.problem(&LIST_1), None);
+        assert_eq!(
+            strict.problem(&LIST_2),
+            Some(Problem::StepTooLarge {
+                index: 1,
+                step: 5,
+                max: 3
+            })
+        );
+        assert_eq!(
+            strict.problem(&LIST_4),
+            Some(Problem::WrongDirection {
+                index: 1,
+                expected: Direction::Increasing
+            })
+        );
+        assert_eq!(
+            strict.problem(&LIST_5),
+            Some(Problem::StepTooSmall {
+                index: 2,
+                step: 0,
+                min: 1
+            })
+        );
+        assert_eq!(strict.problem(&[1]), Some(Problem::TooShort { levels: 1 }));
+
+        let reports = example();
+        let tolerant = strict.removals(1);
+        let explanations: Vec<String> = reports
+            .iter()
+            .enumerate()
+            .filter_map(|(index, report)| explain(index, report, &strict, &tolerant))
+            .collect();
+        assert_eq!(explanations.len(), 4);
+        assert_eq!(
+            explanations[0],
+            "Report 2: 1 2 7 8 9\n  - step of 5 between positions 1 and 2 is larger than 3\n  \
+             - still unsafe after removing 1 level(s)\n"
+        );
+        assert!(explanations[2].ends_with("safe after removing position 1\n"));
+    }
+
+    #[test]
+    fn fuzz_input_bounded() {
+        let corpus = [
+            EXAMPLE.as_bytes().to_vec(),
+            b"2147483647 -1 -2147483648".to_vec(),
+            Reports {
+                reports: 20,
+                ..Default::default()
+            }
+            .generate(1)
+            .into_bytes(),
+        ];
+        run_bounded(&corpus, fuzz_input);
     }
 
     #[test]
     fn test_input() {
-        assert_eq!(check_all_reports(INPUT).unwrap(), 271);
+        let reports = read_reports(INPUT).unwrap();
+        let strict = ReportValidator::default();
+        assert_eq!(count_safe(&reports, &strict), 202);
+        assert_eq!(count_safe(&reports, &strict.removals(1)), 271);
+    }
+
+    /// Whether the report is safe as it is, checking both directions step by step.
+    fn naive_safe(report: &[i32], validator: &ReportValidator) -> bool {
+        let within = |step: i32| validator.min_step <= step && step <= validator.max_step;
+        let increasing = report.windows(2).all(|pair| within(pair[1] - pair[0]));
+        let decreasing = report.windows(2).all(|pair| within(pair[0] - pair[1]));
+        report.len() >= 2
+            && match validator.direction {
+                Some(Direction::Increasing) => increasing,
+                Some(Direction::Decreasing) => decreasing,
+                None => increasing || decreasing,
+            }
+    }
+
+    /// Tries every set of removed levels, fewest first.
+    fn naive_fewest_removals(report: &[i32], validator: &ReportValidator) -> Option<usize> {
+        let n = report.len();
+        (0..1u32 << n)
+            .filter(|removed| removed.count_ones() as usize <= validator.removals)
+            .filter(|removed| {
+                let kept: Vec<i32> = (0..n)
+                    .filter(|i| removed & (1 << i) == 0)
+                    .map(|i| report[i])
+                    .collect();
+                naive_safe(&kept, validator)
+            })
+            .map(|removed| removed.count_ones() as usize)
+            .min()
+    }
+
+    fn validators() -> impl Strategy<Value = ReportValidator> {
+        (
+            0..3,
+            0..4,
+            prop_oneof![
+                Just(None),
+                Just(Some(Direction::Increasing)),
+                Just(Some(Direction::Decreasing))
+            ],
+            0..4usize,
+        )
+            .prop_map(|(min_step, extra, direction, removals)| ReportValidator {
+                min_step,
+                max_step: min_step + extra,
+                direction,
+                removals,
+            })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(5000))]
+
+        #[test]
+        fn matches_naive(report in vec(0..12, 0..10), validator in validators()) {
+            let removed = validator.validate(&report);
+            prop_assert_eq!(
+                removed.as_ref().map(Vec::len),
+                naive_fewest_removals(&report, &validator)
+            );
+
+            if let Some(removed) = removed {
+                let kept: Vec<i32> = (0..report.len())
+                    .filter(|i| !removed.contains(i))
+                    .map(|i| report[i])
+                    .collect();
+                prop_assert!(naive_safe(&kept, &validator));
+            }
+
+            prop_assert_eq!(
+                validator.problem(&report).is_none(),
+                naive_safe(&report, &validator)
+            );
+        }
     }
 }
@@ -1,281 +1,971 @@
-use aoc2024::{print_day_title, print_part_solution};
+use crate::fuzzing::deterministic;
+use crate::{print_day_title, print_part_solution, run_options};
 
 use color_eyre::Result;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
 use std::iter::Peekable;
+use std::ops::Range;
+use tracing::{debug, info_span, trace};
 
 #[allow(dead_code)]
 const TEST_INPUT: &str = "xmul(2,4)%&mul[3,7]!@^do_not_mul(5,5)+mul(32,64]then(mul(11,8)mul(8,5))";
+#[allow(dead_code)]
+const TEST_INPUT_2: &str =
+    "xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?mul(8,5))";
 const INPUT: &str = "assets/input_day03";
 
+/// Longest number allowed as an argument.
+const MAX_DIGITS: usize = 3;
+/// How many bytes the tokenizer reads from its source at once.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Byte range of the input a token or operation was read from.
+pub type Span = Range<usize>;
+
+#[derive(PartialEq, Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    span: Span,
+}
+
 #[derive(PartialEq, Debug, Clone)]
-enum Token {
+enum TokenKind {
     Keyword(Keyword),
     BlockOpen,
     BlockClose,
     Seperator,
-    NumLiteral(i32),
+    NumLiteral(u32),
     Unknown,
 }
 
-#[derive(PartialEq, Debug, Clone)]
-enum Keyword {
-    Multiply,
-    Do,
-    Dont,
+/// Index of an instruction in the [`InstructionSet`] the input was tokenized with.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Keyword(usize);
+
+/// What instructions can change besides the accumulator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct State {
+    /// Whether the values of instructions go into the accumulator.
+    pub enabled: bool,
+}
+
+impl Default for State {
+    fn default() -> Self {
+        Self { enabled: true }
+    }
+}
+
+type Evaluator = Box<dyn Fn(&[i32]) -> i32>;
+type Effect = Box<dyn Fn(&mut State, &[i32])>;
+type Fold<'a, A> = Box<dyn FnMut(&mut A, i32) + 'a>;
+
+/// A keyword followed by `arity` numbers, e.g. `mul(2,4)`.
+///
+/// The value computed by the evaluator goes into the accumulator while the state is
+/// enabled, the effect is applied to the state either way.
+pub struct Instruction {
+    name: String,
+    arity: usize,
+    evaluate: Option<Evaluator>,
+    effect: Option<Effect>,
+}
+
+impl Instruction {
+    pub fn new(name: &str, arity: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            arity,
+            evaluate: None,
+            effect: None,
+        }
+    }
+
+    pub fn evaluate(mut self, evaluate: impl Fn(&[i32]) -> i32 + 'static) -> Self {
+        self.evaluate = Some(Box::new(evaluate));
+        self
+    }
+
+    pub fn effect(mut self, effect: impl Fn(&mut State, &[i32]) + 'static) -> Self {
+        self.effect = Some(Box::new(effect));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn arity(&self) -> usize {
+        self.arity
+    }
+}
+
+/// The instructions the tokenizer recognises and the interpreter runs.
+#[derive(Default)]
+pub struct InstructionSet {
+    instructions: Vec<Instruction>,
+}
+
+impl InstructionSet {
+    /// `mul`, `do` and `don't` as used by the puzzle.
+    pub fn standard() -> Self {
+        InstructionSet::default()
+            .instruction(Instruction::new("mul", 2).evaluate(|args| args[0] * args[1]))
+            .instruction(Instruction::new("do", 0).effect(|state, _| state.enabled = true))
+            .instruction(Instruction::new("don't", 0).effect(|state, _| state.enabled = false))
+    }
+
+    /// Registers an instruction, panics if its name is taken, empty or starts with a digit.
+    pub fn instruction(mut self, instruction: Instruction) -> Self {
+        assert!(
+            !instruction.name.is_empty()
+                && !instruction.name.starts_with(|c: char| c.is_ascii_digit()),
+            "Instruction names can't be empty or start with a digit"
+        );
+        assert!(
+            self.keyword(&instruction.name).is_none(),
+            "Instruction {} is registered twice",
+            instruction.name
+        );
+        self.instructions.push(instruction);
+        self
+    }
+
+    pub fn get(&self, keyword: Keyword) -> &Instruction {
+        &self.instructions[keyword.0]
+    }
+
+    pub fn keyword(&self, name: &str) -> Option<Keyword> {
+        self.instructions
+            .iter()
+            .position(|instruction| instruction.name == name)
+            .map(Keyword)
+    }
+
+    fn longest_name(&self) -> usize {
+        self.instructions
+            .iter()
+            .map(|instruction| instruction.name.len())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// The longest instruction name `rest` starts with, so `don't` wins over `do`.
+    fn match_keyword(&self, rest: &[u8]) -> Option<(Keyword, usize)> {
+        self.instructions
+            .iter()
+            .enumerate()
+            .filter(|(_, instruction)| rest.starts_with(instruction.name.as_bytes()))
+            .max_by_key(|(_, instruction)| instruction.name.len())
+            .map(|(index, instruction)| (Keyword(index), instruction.name.len()))
+    }
 }
 
 pub fn main() -> Result<()> {
     print_day_title(3);
-    print_part_solution(1, "The solution has been lost", "--");
-    let sum = calculate_input(INPUT)?;
-    print_part_solution(2, "Sum of operations:", sum);
+
+    let (sum, enabled_sum) =
+        info_span!("parts", part = "1+2").in_scope(|| calculate_input(File::open(INPUT)?))?;
+    print_part_solution(1, "Sum of operations:", sum);
+    print_part_solution(2, "Sum of enabled operations:", enabled_sum);
+
+    if run_options().explain {
+        let instructions = InstructionSet::standard();
+        let input = std::fs::read(INPUT)?;
+        let program = Program::read_with(input.as_slice(), &instructions)?;
+        println!();
+        print!("{}", explain(&program, &input, &instructions));
+    }
 
     Ok(())
 }
 
-fn calculate_input(file: &str) -> Result<i32> {
-    let mut sum = 0;
-    let input: String = std::fs::read_to_string(file)?;
-
-    let mut tokenizer = Tokenizer::new(&input);
-    tokenizer.tokenize();
+/// Sums up the operations and lists the rejected candidates with the text they were read
+/// from and why.
+fn explain(program: &Program, input: &[u8], instructions: &InstructionSet) -> String {
+    let enabled_sum = Interpreter::summing(instructions).run(&program.operations);
+    let mut explanation = format!(
+        "{} operations adding up to {} while enabled, {} rejected candidates\n",
+        program.operations.len(),
+        enabled_sum,
+        program.rejected.len()
+    );
+    for rejected in &program.rejected {
+        explanation.push_str(&format!(
+            "  - {}..{} {:?}: {}\n",
+            rejected.span.start,
+            rejected.span.end,
+            String::from_utf8_lossy(&input[rejected.span.clone()]),
+            rejected.reason
+        ));
+    }
+    explanation
+}
 
-    let mut nice = Parser::new(tokenizer.read_tokens.clone());
-    nice.parse();
+/// Streams the input through the tokenizer, parser and two interpreters at once, the
+/// first ignoring `do()` and `don't()`, the second honouring them.
+pub fn calculate_input(input: impl Read) -> io::Result<(i64, i64)> {
+    let instructions = InstructionSet::standard();
+    let ignore = ParserConfig {
+        conditionals: Conditionals::Ignore,
+    };
+    let honour = ParserConfig {
+        conditionals: Conditionals::Honour,
+    };
+
+    let mut all = Interpreter::summing(&instructions);
+    let mut enabled = Interpreter::summing(&instructions);
+    let (mut recognised, mut rejected) = (0, 0);
+
+    let mut tokens = Tokenizer::new(input, &instructions);
+    for parsed in Parser::new(&mut tokens, &instructions, honour) {
+        match parsed {
+            Ok(operation) => {
+                recognised += 1;
+                if ignore.keeps(instructions.get(operation.op_type)) {
+                    all.execute(&operation);
+                }
+                enabled.execute(&operation);
+            }
+            Err(candidate) => {
+                rejected += 1;
+                trace!(
+                    "Rejected {} at {:?}: {}",
+                    instructions.get(candidate.keyword).name,
+                    candidate.span,
+                    candidate.reason
+                );
+            }
+        }
+    }
+    tokens.finish()?;
 
-    sum += calculate_operations(nice.operations).unwrap();
+    debug!(
+        "Recognised {} operations, rejected {} candidates",
+        recognised, rejected
+    );
+    Ok((all.finish(), enabled.finish()))
+}
 
-    Ok(sum)
+/// Fuzz target: the tokens of any input are the same every time and cover it without
+/// gaps or overlaps.
+pub fn fuzz_tokenizer(data: &[u8]) {
+    let instructions = InstructionSet::standard();
+    let tokens = deterministic(data, |data| {
+        Tokenizer::new(data, &instructions).collect::<Vec<_>>()
+    });
+
+    let mut end = 0;
+    for token in &tokens {
+        assert_eq!(token.span.start, end, "Tokens don't line up: {:?}", tokens);
+        assert!(token.span.end > token.span.start, "Empty token {:?}", token);
+        end = token.span.end;
+    }
+    assert_eq!(end, data.len(), "Tokens don't cover the input");
 }
 
-fn calculate_operations(operations: Vec<Operation>) -> Option<i32> {
-    let mut sum = 0;
-    for op in operations {
-        match op.op_type {
-            Keyword::Multiply => sum += op.num_1? * op.num_2?,
-            _ => todo!("Unimplemented keyword"),
-        }
+/// Fuzz target: any input parses the same way every time, with operations and rejected
+/// candidates in input order. A rejected span ends with the offending token, which may
+/// start the next candidate, so only the starts are strictly increasing.
+pub fn fuzz_parser(data: &[u8]) {
+    let instructions = InstructionSet::standard();
+    let parsed = deterministic(data, |data| {
+        let config = ParserConfig {
+            conditionals: Conditionals::Honour,
+        };
+        let tokens = Tokenizer::new(data, &instructions);
+        Parser::new(tokens, &instructions, config).collect::<Vec<_>>()
+    });
+
+    let spans = parsed.iter().map(|parsed| match parsed {
+        Ok(operation) => &operation.span,
+        Err(rejected) => &rejected.span,
+    });
+    let mut previous = None;
+    for span in spans {
+        assert!(
+            previous.is_none_or(|start| span.start > start)
+                && span.start < span.end
+                && span.end <= data.len(),
+            "Spans out of order: {:?}",
+            parsed
+        );
+        previous = Some(span.start);
     }
+}
 
-    Some(sum)
+/// Fuzz target: both sums of any input are the same every time.
+pub fn fuzz_input(data: &[u8]) {
+    deterministic(data, |data| {
+        calculate_input(data).map_err(|error| error.to_string())
+    })
+    .expect("reading from a slice never fails");
 }
 
-#[derive(Debug)]
-struct Operation {
-    op_type: Keyword,
-    num_1: Option<i32>,
-    num_2: Option<i32>,
+/// Runs operations, folding the values of enabled instructions into an accumulator.
+pub struct Interpreter<'a, A> {
+    instructions: &'a InstructionSet,
+    state: State,
+    accumulator: A,
+    fold: Fold<'a, A>,
 }
 
-impl Operation {
-    fn new(op_type: Keyword) -> Self {
+impl<'a> Interpreter<'a, i64> {
+    pub fn summing(instructions: &'a InstructionSet) -> Self {
+        Self::new(instructions, 0, |sum, value| *sum += value as i64)
+    }
+}
+
+impl<'a, A> Interpreter<'a, A> {
+    pub fn new(
+        instructions: &'a InstructionSet,
+        accumulator: A,
+        fold: impl FnMut(&mut A, i32) + 'a,
+    ) -> Self {
         Self {
-            op_type,
-            num_1: None,
-            num_2: None,
+            instructions,
+            state: State::default(),
+            accumulator,
+            fold: Box::new(fold),
+        }
+    }
+
+    pub fn execute(&mut self, operation: &Operation) {
+        let instruction = self.instructions.get(operation.op_type);
+        if let Some(evaluate) = &instruction.evaluate {
+            if self.state.enabled {
+                (self.fold)(&mut self.accumulator, evaluate(&operation.args));
+            }
+        }
+        if let Some(effect) = &instruction.effect {
+            effect(&mut self.state, &operation.args);
+        }
+    }
+
+    pub fn run<'o>(mut self, operations: impl IntoIterator<Item = &'o Operation>) -> A {
+        for operation in operations {
+            self.execute(operation);
         }
+        self.finish()
     }
 
-    fn set_num_1(&mut self, num: i32) {
-        self.num_1 = Some(num)
+    pub fn finish(self) -> A {
+        self.accumulator
     }
+}
 
-    fn set_num_2(&mut self, num: i32) {
-        if self.num_1.is_none() {
-            panic!("Tried to set num_2 with num_1 as None")
+#[derive(Debug, PartialEq)]
+pub struct Operation {
+    pub op_type: Keyword,
+    pub args: Vec<i32>,
+    pub span: Span,
+}
+
+/// A keyword that did not start a valid operation.
+#[derive(Debug, PartialEq)]
+pub struct Rejected {
+    pub keyword: Keyword,
+    /// From the keyword up to and including the offending token.
+    pub span: Span,
+    pub reason: RejectReason,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RejectReason {
+    MissingBlockOpen,
+    MissingNumber,
+    NumberTooLong,
+    MissingSeperator,
+    MissingBlockClose,
+    UnexpectedEnd,
+}
+
+impl fmt::Display for RejectReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RejectReason::MissingBlockOpen => write!(f, "expected '('"),
+            RejectReason::MissingNumber => write!(f, "expected a number"),
+            RejectReason::NumberTooLong => {
+                write!(f, "number has more than {} digits", MAX_DIGITS)
+            }
+            RejectReason::MissingSeperator => write!(f, "expected ','"),
+            RejectReason::MissingBlockClose => write!(f, "expected ')'"),
+            RejectReason::UnexpectedEnd => write!(f, "input ended"),
         }
-        self.num_2 = Some(num)
     }
+}
 
-    fn no_num_set(&self) -> bool {
-        self.num_1.is_none() && self.num_2.is_none()
+/// Everything the parser read from an input, in input order.
+#[derive(Debug, Default)]
+pub struct Program {
+    /// Every operation, `do()` and `don't()` included.
+    pub operations: Vec<Operation>,
+    pub rejected: Vec<Rejected>,
+}
+
+impl Program {
+    /// Parses the whole input with the standard instructions.
+    pub fn read(input: impl Read) -> io::Result<Self> {
+        Self::read_with(input, &InstructionSet::standard())
     }
 
-    fn only_num_1_set(&self) -> bool {
-        self.num_1.is_some() && self.num_2.is_none()
+    /// Parses the whole input, recognising the keywords of `instructions`.
+    pub fn read_with(input: impl Read, instructions: &InstructionSet) -> io::Result<Self> {
+        let config = ParserConfig {
+            conditionals: Conditionals::Honour,
+        };
+        let mut tokens = Tokenizer::new(input, instructions);
+        let program = Parser::new(&mut tokens, instructions, config).parse();
+        tokens.finish()?;
+        Ok(program)
     }
+}
+
+/// Whether `do()` and `don't()` switch multiplications on and off.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Conditionals {
+    /// Instructions without a value, like `do()` and `don't()`, are recognised but left
+    /// out of the program.
+    Ignore,
+    Honour,
+}
 
-    fn is_valid_mul(&self) -> bool {
-        self.num_1.is_some() && self.num_2.is_some()
+#[derive(Debug, Clone, Copy)]
+struct ParserConfig {
+    conditionals: Conditionals,
+}
+
+impl ParserConfig {
+    /// Whether operations of the instruction end up in the program.
+    fn keeps(&self, instruction: &Instruction) -> bool {
+        instruction.evaluate.is_some() || self.conditionals == Conditionals::Honour
     }
 }
 
-#[derive(Debug)]
-struct Parser {
-    tokens: Vec<Token>,
-    last_token: Token,
-    current_operation: Option<Operation>,
-    operations: Vec<Operation>,
-    do_mode: bool,
+/// Recognises operations with the grammar
+///
+/// ```text
+/// operation := keyword "(" arguments ")"
+/// arguments := number ("," number){arity - 1}   for instructions taking numbers
+///            | ""                               otherwise
+/// number    := digit{1,3}
+/// ```
+///
+/// Tokens between operations are skipped. A keyword that does not start a valid
+/// operation is rejected, parsing resumes at the token that broke the grammar.
+///
+/// The parser pulls tokens as it goes and yields every operation or rejected candidate
+/// as soon as it is complete.
+struct Parser<'a, I: Iterator<Item = Token>> {
+    tokens: Peekable<I>,
+    instructions: &'a InstructionSet,
+    config: ParserConfig,
+    last_end: usize,
 }
 
-impl Parser {
-    fn new(tokens: Vec<Token>) -> Parser {
-        Self {
-            tokens,
-            last_token: Token::Unknown,
-            current_operation: None,
-            operations: vec![],
-            do_mode: true,
+impl<'a, I: Iterator<Item = Token>> Parser<'a, I> {
+    fn new(
+        tokens: impl IntoIterator<IntoIter = I>,
+        instructions: &'a InstructionSet,
+        config: ParserConfig,
+    ) -> Self {
+        Parser {
+            tokens: tokens.into_iter().peekable(),
+            instructions,
+            config,
+            last_end: 0,
         }
     }
 
-    fn parse(&mut self) {
-        // valid block:
-        // Keyword(Multiply),BlockOpen,NumLiteral,Seperator,
-        // NumLiteral,BlockClose
-        for token in self.tokens.clone().into_iter() {
-            if let Some(operation) = self.current_operation.as_ref() {
-                if operation.num_1 == Some(8) && operation.num_2 == Some(5) {
-                    panic!("I am evil");
-                }
+    fn parse(self) -> Program {
+        let mut program = Program::default();
+        for parsed in self {
+            match parsed {
+                Ok(operation) => program.operations.push(operation),
+                Err(rejected) => program.rejected.push(rejected),
             }
-            let cur_token = token.clone();
-            if self.current_operation.is_none() {
-                if let Token::Keyword(keyword) = token {
-                    self.current_operation = Some(Operation::new(keyword))
-                }
-                self.last_token = cur_token;
-                continue;
+        }
+        program
+    }
+
+    fn parse_operation(&mut self, keyword: Keyword, start: usize) -> Result<Operation, Rejected> {
+        let reject = |(end, reason)| Rejected {
+            keyword,
+            span: start..end,
+            reason,
+        };
+
+        self.expect(&TokenKind::BlockOpen, RejectReason::MissingBlockOpen)
+            .map_err(reject)?;
+
+        let arity = self.instructions.get(keyword).arity;
+        let mut args = Vec::with_capacity(arity);
+        for i in 0..arity {
+            if i > 0 {
+                self.expect(&TokenKind::Seperator, RejectReason::MissingSeperator)
+                    .map_err(reject)?;
             }
-            match token {
-                Token::Keyword(keyword) => self.current_operation = Some(Operation::new(keyword)),
-                Token::BlockOpen => self.parse_block_open(),
-                Token::BlockClose => self.parse_block_close(),
-                Token::Seperator => self.parse_seperator(),
-                Token::NumLiteral(num) => self.parse_num_literal(num),
-                Token::Unknown => self.current_operation = None,
+            args.push(self.expect_number().map_err(reject)?);
+        }
+
+        let end = self
+            .expect(&TokenKind::BlockClose, RejectReason::MissingBlockClose)
+            .map_err(reject)?;
+
+        Ok(Operation {
+            op_type: keyword,
+            args,
+            span: start..end,
+        })
+    }
+
+    /// Consumes the next token if it is of `kind` and returns its end. Otherwise returns
+    /// the end of the offending token, which is not consumed, and why it was rejected.
+    fn expect(
+        &mut self,
+        kind: &TokenKind,
+        reason: RejectReason,
+    ) -> Result<usize, (usize, RejectReason)> {
+        match self.tokens.peek() {
+            Some(token) if token.kind == *kind => {
+                Ok(self.advance().map_or(0, |token| token.span.end))
             }
-            self.last_token = cur_token;
-            // println!("Reading complete:");
-            // println!("Current Operation: {:#?}", self.current_operation);
+            Some(token) => Err((token.span.end, reason)),
+            None => Err((self.last_end, RejectReason::UnexpectedEnd)),
         }
     }
 
-    fn current_op_only_num_1(&self) -> bool {
-        self.current_operation
-            .as_ref()
-            .map(|op| op.only_num_1_set())
-            .unwrap_or(false)
+    fn expect_number(&mut self) -> Result<i32, (usize, RejectReason)> {
+        match self.tokens.peek() {
+            Some(Token {
+                kind: TokenKind::NumLiteral(num),
+                span,
+            }) => {
+                if span.len() > MAX_DIGITS {
+                    return Err((span.end, RejectReason::NumberTooLong));
+                }
+                let num = *num as i32;
+                self.advance();
+                Ok(num)
+            }
+            Some(token) => Err((token.span.end, RejectReason::MissingNumber)),
+            None => Err((self.last_end, RejectReason::UnexpectedEnd)),
+        }
     }
 
-    fn current_op_no_num(&self) -> bool {
-        self.current_operation
-            .as_ref()
-            .map(|op| op.no_num_set())
-            .unwrap_or(false)
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.next()?;
+        self.last_end = token.span.end;
+        Some(token)
     }
+}
+
+impl<I: Iterator<Item = Token>> Iterator for Parser<'_, I> {
+    type Item = Result<Operation, Rejected>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let token = self.advance()?;
+            let TokenKind::Keyword(keyword) = token.kind else {
+                continue;
+            };
 
-    fn parse_block_open(&mut self) {
-        if !matches!(self.last_token, Token::Keyword(_)) {
-            self.current_operation = None;
+            match self.parse_operation(keyword, token.span.start) {
+                Ok(_) if !self.config.keeps(self.instructions.get(keyword)) => continue,
+                parsed => return Some(parsed),
+            }
         }
     }
+}
 
-    fn parse_block_close(&mut self) {
-        if let Some(operation) = self.current_operation.take() {
-            match operation.op_type {
-                Keyword::Multiply if self.do_mode && operation.is_valid_mul() => {
-                    self.operations.push(operation)
-                }
-                Keyword::Do => self.do_mode = true,
-                Keyword::Dont => self.do_mode = false,
-                _ => self.current_operation = None,
-            }
+/// Reads tokens from any byte source, only keeping a window of about [`CHUNK_SIZE`]
+/// bytes in memory. Spans are offsets into the whole stream.
+///
+/// A read error ends the tokens, it is returned by [`Tokenizer::finish`].
+struct Tokenizer<'a, R> {
+    source: R,
+    instructions: &'a InstructionSet,
+    buffer: Vec<u8>,
+    /// Index of the next byte to tokenize in `buffer`.
+    cursor: usize,
+    /// Stream offset of `buffer[0]`.
+    offset: usize,
+    exhausted: bool,
+    error: Option<io::Error>,
+}
+
+impl<'a, R: Read> Tokenizer<'a, R> {
+    fn new(source: R, instructions: &'a InstructionSet) -> Tokenizer<'a, R> {
+        Tokenizer {
+            source,
+            instructions,
+            buffer: Vec::with_capacity(CHUNK_SIZE),
+            cursor: 0,
+            offset: 0,
+            exhausted: false,
+            error: None,
         }
     }
 
-    fn parse_seperator(&mut self) {
-        if !matches!(self.last_token, Token::NumLiteral(_)) || !self.current_op_only_num_1() {
-            self.current_operation = None;
+    fn finish(self) -> io::Result<()> {
+        match self.error {
+            Some(error) => Err(error),
+            None => Ok(()),
         }
     }
 
-    fn parse_num_literal(&mut self, num: i32) {
-        match self.last_token {
-            Token::BlockOpen if self.current_op_no_num() => {
-                if let Some(operation) = self.current_operation.as_mut() {
-                    operation.set_num_1(num);
-                } else {
-                    panic!("Tried to modify a non existing cur op")
+    fn location(&self) -> usize {
+        self.offset + self.cursor
+    }
+
+    fn rest(&self) -> &[u8] {
+        &self.buffer[self.cursor..]
+    }
+
+    /// Reads until `len` bytes are buffered past the cursor or the source is exhausted.
+    fn fill(&mut self, len: usize) {
+        while self.rest().len() < len && !self.exhausted {
+            self.buffer.drain(..self.cursor);
+            self.offset += self.cursor;
+            self.cursor = 0;
+
+            let filled = self.buffer.len();
+            self.buffer.resize(filled + CHUNK_SIZE, 0);
+            match self.source.read(&mut self.buffer[filled..]) {
+                Ok(read) => {
+                    self.buffer.truncate(filled + read);
+                    self.exhausted = read == 0;
                 }
-            }
-            Token::Seperator if self.current_op_only_num_1() => {
-                if let Some(operation) = self.current_operation.as_mut() {
-                    operation.set_num_2(num);
-                } else {
-                    panic!("Tried to modify a non existing cur op")
+                Err(error) => {
+                    self.buffer.truncate(filled);
+                    if error.kind() != io::ErrorKind::Interrupted {
+                        self.error = Some(error);
+                        self.exhausted = true;
+                    }
                 }
             }
-            _ => {
-                self.current_operation = None;
-            }
         }
     }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.fill(self.instructions.longest_name().max(1));
+        let start = self.location();
+        let byte = *self.rest().first()?;
+
+        let kind = match byte {
+            b'(' => TokenKind::BlockOpen,
+            b')' => TokenKind::BlockClose,
+            b',' => TokenKind::Seperator,
+            byte if byte.is_ascii_digit() => {
+                let mut literal = 0u32;
+                while let Some(&digit) = self.rest().first().filter(|b| b.is_ascii_digit()) {
+                    literal = literal
+                        .saturating_mul(10)
+                        .saturating_add((digit - b'0') as u32);
+                    self.cursor += 1;
+                    self.fill(1);
+                }
+                return Some(Token {
+                    kind: TokenKind::NumLiteral(literal),
+                    span: start..self.location(),
+                });
+            }
+            _ => match self.instructions.match_keyword(self.rest()) {
+                Some((keyword, len)) => {
+                    self.cursor += len;
+                    return Some(Token {
+                        kind: TokenKind::Keyword(keyword),
+                        span: start..self.location(),
+                    });
+                }
+                None => TokenKind::Unknown,
+            },
+        };
+
+        self.cursor += 1;
+        Some(Token {
+            kind,
+            span: start..self.location(),
+        })
+    }
 }
 
-struct Tokenizer<'a> {
-    phrase: Peekable<std::str::Chars<'a>>,
-    pub read_tokens: Vec<Token>,
+impl<R: Read> Iterator for Tokenizer<'_, R> {
+    type Item = Token;
+
+    fn next(&mut self) -> Option<Token> {
+        self.next_token()
+    }
 }
 
-impl Tokenizer<'_> {
-    fn new(phrase: &str) -> Tokenizer<'_> {
-        Tokenizer {
-            phrase: phrase.chars().peekable(),
-            read_tokens: Vec::default(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzing::run_bounded;
+    use crate::generate::{CorruptedMemory, Generator};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    fn parse_with(input: &str, instructions: &InstructionSet) -> Program {
+        Program::read_with(input.as_bytes(), instructions).unwrap()
+    }
+
+    fn parse(input: &str) -> Program {
+        parse_with(input, &InstructionSet::standard())
+    }
+
+    fn calculate(input: &str) -> (i64, i64) {
+        calculate_input(input.as_bytes()).unwrap()
+    }
+
+    /// Hands out one byte per read, so every token crosses a buffer refill.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Some((&byte, rest)) = self.0.split_first() else {
+                return Ok(0);
+            };
+            buf[0] = byte;
+            self.0 = rest;
+            Ok(1)
         }
     }
 
-    fn tokenize(&mut self) {
-        while let Some(char) = self.phrase.next() {
-            self.read_tokens.push(match char {
-                '(' => Token::BlockOpen,
-                ')' => Token::BlockClose,
-                ',' => Token::Seperator,
-                char if char.is_ascii_digit() => {
-                    let mut literal: String = char.to_string();
-                    while let Some(next) = self.phrase.next_if(|&x| x.is_ascii_digit()) {
-                        if literal.len() == 3 {
-                            panic!("Tried to create a NumLiteral with len > 3");
-                        }
-                        literal.push(next);
-                    }
-                    Token::NumLiteral(literal.parse().expect("failed to parse NumLiteral"))
+    /// Fails once the input is used up.
+    struct Broken<'a>(&'a [u8]);
+
+    impl Read for Broken<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.0.read(buf)? {
+                0 => Err(io::Error::other("disk on fire")),
+                read => Ok(read),
+            }
+        }
+    }
+
+    fn keyword(name: &str) -> Keyword {
+        InstructionSet::standard().keyword(name).unwrap()
+    }
+
+    #[test]
+    fn token_spans() {
+        let instructions = InstructionSet::standard();
+        let tokens = Tokenizer::new("xmul(12,don't)".as_bytes(), &instructions);
+        let spans: Vec<(TokenKind, Span)> = tokens.map(|token| (token.kind, token.span)).collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                (TokenKind::Unknown, 0..1),
+                (TokenKind::Keyword(keyword("mul")), 1..4),
+                (TokenKind::BlockOpen, 4..5),
+                (TokenKind::NumLiteral(12), 5..7),
+                (TokenKind::Seperator, 7..8),
+                (TokenKind::Keyword(keyword("don't")), 8..13),
+                (TokenKind::BlockClose, 13..14),
+            ]
+        );
+    }
+
+    #[test]
+    fn recognised_operations() {
+        let program = parse("xmul(2,4)&mul[3,7]!^don't()_mul(5,5)+mul(32,64](mul(11,8)undo()?");
+
+        let operations: Vec<(Keyword, Vec<i32>, Span)> = program
+            .operations
+            .into_iter()
+            .map(|op| (op.op_type, op.args, op.span))
+            .collect();
+        assert_eq!(
+            operations,
+            vec![
+                (keyword("mul"), vec![2, 4], 1..9),
+                (keyword("don't"), vec![], 20..27),
+                (keyword("mul"), vec![5, 5], 28..36),
+                (keyword("mul"), vec![11, 8], 48..57),
+                (keyword("do"), vec![], 59..63),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejected_candidates() {
+        let program = parse("mul[3,7]mul(32,64]mul(1234,5)mul(4*do(");
+
+        let rejected: Vec<(Span, RejectReason)> = program
+            .rejected
+            .into_iter()
+            .map(|rejected| (rejected.span, rejected.reason))
+            .collect();
+        assert_eq!(
+            rejected,
+            vec![
+                (0..4, RejectReason::MissingBlockOpen),
+                (8..18, RejectReason::MissingBlockClose),
+                (18..26, RejectReason::NumberTooLong),
+                (29..35, RejectReason::MissingSeperator),
+                (35..38, RejectReason::UnexpectedEnd),
+            ]
+        );
+    }
+
+    #[test]
+    fn example_part_1() {
+        assert_eq!(calculate(TEST_INPUT).0, 161);
+        assert_eq!(calculate(TEST_INPUT_2).0, 161);
+    }
+
+    #[test]
+    fn example_part_2() {
+        assert_eq!(calculate(TEST_INPUT_2).1, 48);
+        assert_eq!(calculate(TEST_INPUT).1, 161);
+    }
+
+    #[test]
+    fn streamed_input() {
+        let instructions = InstructionSet::standard();
+        let whole: Vec<Token> = Tokenizer::new(TEST_INPUT_2.as_bytes(), &instructions).collect();
+        let trickled: Vec<Token> =
+            Tokenizer::new(Trickle(TEST_INPUT_2.as_bytes()), &instructions).collect();
+        assert_eq!(trickled, whole);
+
+        let padded = format!("{}{}", "x".repeat(CHUNK_SIZE - 6), TEST_INPUT_2);
+        assert_eq!(calculate(&padded), (161, 48));
+
+        assert!(calculate_input(Broken(TEST_INPUT_2.as_bytes())).is_err());
+    }
+
+    #[test]
+    fn custom_instructions() {
+        let instructions = InstructionSet::default()
+            .instruction(Instruction::new("add", 2).evaluate(|args| args[0] + args[1]))
+            .instruction(Instruction::new("sub", 2).evaluate(|args| args[0] - args[1]))
+            .instruction(Instruction::new("mul3", 3).evaluate(|args| args[0] * args[1] * args[2]))
+            .instruction(Instruction::new("mul", 2).evaluate(|args| args[0] * args[1]))
+            .instruction(Instruction::new("skip", 0).effect(|state, _| state.enabled = false))
+            .instruction(Instruction::new("resume", 0).effect(|state, _| state.enabled = true));
+
+        let program = parse_with(
+            "add(1,2)sub(10,4)]mul3(2,3,4)mul(2,2)mul3(1,1)skip()add(100,100)resume()sub(0,1)",
+            &instructions,
+        );
+        assert_eq!(program.operations.len(), 8);
+        assert_eq!(program.rejected.len(), 1);
+
+        let sum = Interpreter::summing(&instructions).run(&program.operations);
+        assert_eq!(sum, 3 + 6 + 24 + 4 - 1);
+    }
+
+    #[test]
+    fn configurable_accumulator() {
+        let instructions = InstructionSet::standard();
+        let program = parse(TEST_INPUT_2);
+
+        let values = Interpreter::new(&instructions, vec![], |values: &mut Vec<i32>, value| {
+            values.push(value)
+        })
+        .run(&program.operations);
+        assert_eq!(values, vec![8, 40]);
+
+        let max = Interpreter::new(&instructions, i32::MIN, |max, value| {
+            *max = (*max).max(value)
+        })
+        .run(&program.operations);
+        assert_eq!(max, 40);
+    }
+
+    /// Looks for every instruction at every byte, like the puzzle's regular expression.
+    fn naive_calculate(input: &str) -> (i64, i64) {
+        let bytes = input.as_bytes();
+        let number = |at: usize| {
+            let digits = bytes[at..]
+                .iter()
+                .take_while(|b| b.is_ascii_digit())
+                .count();
+            (1..=MAX_DIGITS).contains(&digits).then(|| {
+                let value: i64 = input[at..at + digits].parse().unwrap();
+                (value, at + digits)
+            })
+        };
+        let mul = |at: usize| {
+            let (a, at) = number(at)?;
+            (bytes.get(at) == Some(&b',')).then_some(())?;
+            let (b, at) = number(at + 1)?;
+            (bytes.get(at) == Some(&b')')).then_some(a * b)
+        };
+
+        let (mut all, mut enabled, mut on) = (0, 0, true);
+        for at in 0..bytes.len() {
+            let rest = &input[at..];
+            if rest.starts_with("do()") {
+                on = true;
+            } else if rest.starts_with("don't()") {
+                on = false;
+            } else if let Some(product) = rest.starts_with("mul(").then(|| mul(at + 4)).flatten() {
+                all += product;
+                if on {
+                    enabled += product;
                 }
-                _ => match self.match_keyword(char) {
-                    Some(keyword) => match keyword {
-                        Keyword::Multiply => {
-                            self.phrase.next();
-                            self.phrase.next();
-                            Token::Keyword(keyword)
-                        }
-                        Keyword::Do => {
-                            self.phrase.next();
-                            Token::Keyword(keyword)
-                        }
-                        Keyword::Dont => {
-                            self.phrase.next();
-                            self.phrase.next();
-                            self.phrase.next();
-                            self.phrase.next();
-                            Token::Keyword(keyword)
-                        }
-                    },
-                    None => Token::Unknown,
-                },
-            });
+            }
         }
+        (all, enabled)
     }
 
-    fn match_keyword(&self, first_char: char) -> Option<Keyword> {
-        let cloned_iter = self.phrase.clone();
-        let keyword: &str = &(first_char.to_string() + &cloned_iter.take(6).collect::<String>());
-        match keyword {
-            k if k.starts_with("mul") => Some(Keyword::Multiply),
-            k if k.starts_with("don't") => Some(Keyword::Dont),
-            k if k.starts_with("do") => Some(Keyword::Do),
-            _ => None,
+    /// Corrupted memory made of instruction parts, so many almost valid candidates show up.
+    fn corrupted() -> impl Strategy<Value = String> {
+        let fragment = select(&[
+            "mul(", "mul", "do()", "don't()", "do", "don't", "(", ")", ",", "0", "7", "42", "999",
+            "1000", "x", " ", "-", "'",
+        ]);
+        vec(fragment, 0..40).prop_map(|fragments| fragments.concat())
+    }
+
+    fn fuzz_corpus() -> Vec<Vec<u8>> {
+        vec![
+            TEST_INPUT.as_bytes().to_vec(),
+            TEST_INPUT_2.as_bytes().to_vec(),
+            b"mul(1234,5)mul(4294967296,1)mul(1,".to_vec(),
+            CorruptedMemory { bytes: 300 }.generate(1).into_bytes(),
+        ]
+    }
+
+    #[test]
+    fn explains_rejected_candidates() {
+        let program = Program::read(TEST_INPUT_2.as_bytes()).unwrap();
+        let instructions = InstructionSet::standard();
+        assert_eq!(
+            explain(&program, TEST_INPUT_2.as_bytes(), &instructions),
+            "6 operations adding up to 48 while enabled, 2 rejected candidates\n  \
+             - 10..14 \"mul[\": expected '('\n  \
+             - 37..47 \"mul(32,64]\": expected ')'\n"
+        );
+        assert!(Program::read(Broken(b"mul(1,2)")).is_err());
+    }
+
+    #[test]
+    fn fuzz_tokenizer_bounded() {
+        run_bounded(&fuzz_corpus(), fuzz_tokenizer);
+    }
+
+    #[test]
+    fn fuzz_parser_bounded() {
+        run_bounded(&fuzz_corpus(), fuzz_parser);
+    }
+
+    #[test]
+    fn fuzz_input_bounded() {
+        run_bounded(&fuzz_corpus(), fuzz_input);
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(3000))]
+
+        #[test]
+        fn matches_naive(input in corrupted()) {
+            prop_assert_eq!(calculate(&input), naive_calculate(&input));
         }
     }
 }
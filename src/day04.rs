@@ -1,205 +1,169 @@
-use aoc2024::{print_day_title, print_part_solution, read_lines};
-use color_eyre::Result;
+use crate::fuzzing::deterministic;
+use crate::highlight::{html_page, Highlight};
+use crate::stencil::{Stencil, StencilMatcher};
+use crate::word_search::WordSearch;
+use crate::{print_day_title, print_part_solution, read_lines, run_options};
+use color_eyre::{eyre::eyre, Result};
 use std::marker::PhantomData;
+use tracing::info_span;
 
 #[allow(dead_code)]
 const TEST_INPUT: &str = "assets/test_input_day04";
 const INPUT: &str = "assets/input_day04";
 
+#[derive(Debug)]
 struct Part1;
+#[derive(Debug)]
 struct Part2;
 
 #[derive(Debug)]
 struct Input<Part> {
     data: Vec<Vec<char>>,
-    target: Vec<Vec<char>>,
+    target: Vec<String>,
     _marker: PhantomData<Part>,
 }
 
 pub fn main() -> Result<()> {
     print_day_title(4);
 
-    let matches = Input::<Part1>::default().read_file(INPUT)?.parse()?;
+    let highlight = run_options().highlight.is_enabled();
+
+    let (matches, xmas) = info_span!("part", part = 1)
+        .in_scope(|| Input::<Part1>::default().read_file(INPUT)?.parse(highlight))?;
     print_part_solution(1, "Total of matches:", matches);
 
-    let matches = Input::<Part2>::default().read_file(INPUT)?.parse()?;
+    let (matches, x_mas) = info_span!("part", part = 2)
+        .in_scope(|| Input::<Part2>::default().read_file(INPUT)?.parse(highlight))?;
     print_part_solution(2, "Total of matches:", matches);
 
+    if let (Some(xmas), Some(x_mas)) = (xmas, x_mas) {
+        show_highlights(&[("Part 1: XMAS", xmas), ("Part 2: X-MAS", x_mas)])?;
+    }
+
     Ok(())
 }
 
-impl<Part> Input<Part> {
-    fn read_file(mut self, file: &str) -> Result<Self> {
-        let lines = read_lines(file)?;
-
-        lines.map_while(Result::ok).for_each(|line| {
-            let line: Vec<char> = line.chars().collect();
-            self.add_line(line);
-        });
-
-        Ok(self)
+/// Prints and exports the matched cells of both parts as requested on the command line.
+fn show_highlights(highlights: &[(&str, Highlight)]) -> Result<()> {
+    let options = &run_options().highlight;
+    let highlights: Vec<(&str, Highlight)> = highlights
+        .iter()
+        .map(|(title, highlight)| (*title, highlight.clone().only_matched(options.only_matched)))
+        .collect();
+
+    if options.print {
+        for (title, highlight) in &highlights {
+            println!();
+            println!("{}", title);
+            print!("{}", highlight.render());
+        }
     }
 
-    fn add_line(&mut self, line: Vec<char>) {
-        self.data.push(line)
+    if let Some(path) = &options.html {
+        let sections: Vec<(&str, &Highlight)> = highlights
+            .iter()
+            .map(|(title, highlight)| (*title, highlight))
+            .collect();
+        std::fs::write(path, html_page("Day 4: Ceres Search", &sections))?;
     }
 
-    fn loop_through_input<F, G>(&self, continue_condition: G, action: F) -> Result<i32>
-    where
-        G: Fn(char) -> bool,
-        F: Fn(&Self, (usize, usize)) -> Result<i32>,
-    {
-        let mut matches = 0;
+    Ok(())
+}
 
-        for (y, row) in self.data.iter().enumerate() {
-            for (x, &col) in row.iter().enumerate() {
-                if continue_condition(col) {
-                    continue;
-                }
+impl<Part> Input<Part> {
+    fn read_file(self, file: &str) -> Result<Self> {
+        let lines = read_lines(file)?;
+        self.parse_lines(lines.map_while(Result::ok))
+    }
 
-                matches += action(self, (x, y))?;
-            }
+    /// Adds the non-empty lines as rows of the grid, which has to be rectangular.
+    fn parse_lines(mut self, lines: impl Iterator<Item = String>) -> Result<Self> {
+        for line in lines.filter(|line| !line.is_empty()) {
+            self.add_line(line.chars().collect())?;
         }
+        Ok(self)
+    }
 
-        Ok(matches)
+    fn add_line(&mut self, line: Vec<char>) -> Result<()> {
+        if let Some(first) = self.data.first() {
+            if first.len() != line.len() {
+                return Err(eyre!(
+                    "Row {} has {} letters, expected {}",
+                    self.data.len() + 1,
+                    line.len(),
+                    first.len()
+                ));
+            }
+        }
+        self.data.push(line);
+        Ok(())
     }
 
-    fn get_at_position(&self, position: (usize, usize)) -> Option<char> {
-        let (x, y) = position;
-        self.data.get(y)?.get(x).copied()
+    fn size(&self) -> (usize, usize) {
+        (self.data.first().map_or(0, Vec::len), self.data.len())
     }
 }
 
 impl Input<Part1> {
-    fn parse(&self) -> Result<i32> {
-        self.loop_through_input(|char| char != 'X', Self::check_at_x)
-    }
-
-    fn check_at_x(&self, position: (usize, usize)) -> Result<i32> {
-        let directions = [
-            (1, 0),   // forward: x++
-            (-1, 0),  // backward: x--
-            (0, -1),  // up: y--
-            (0, 1),   // down y++
-            (-1, 1),  // backward/down: x--, y++
-            (-1, -1), // backward/up: x--, y--
-            (1, -1),  // forward/up: x++,y--
-            (1, 1),   // forward/down: x++, y++
-        ];
-
-        let mut matches = 0;
-        for (dr, dc) in directions {
-            if self.check_direction(position, (dr, dc)) {
-                matches += 1;
+    /// Counts the matches and, if `highlight` is set, marks their cells.
+    fn parse(&self, highlight: bool) -> Result<(i32, Option<Highlight>)> {
+        let matches = WordSearch::new(&self.target).find(&self.data);
+
+        let highlight = highlight.then(|| {
+            let (width, height) = self.size();
+            let mut highlight = Highlight::new(&self.data);
+            for found in &matches {
+                highlight.mark(found.cells(width, height));
             }
-        }
+            highlight
+        });
 
-        Ok(matches)
+        Ok((matches.len() as i32, highlight))
     }
+}
 
-    fn check_direction(&self, start: (usize, usize), direction: (i8, i8)) -> bool {
-        let (mut x, mut y) = start;
-        let (dx, dy) = direction;
-        let length = self.target[0].len();
-        let mut i = 0;
-
-        while let Some(value) = self.get_at_position((x, y)) {
-            if self.target[0][i] != value {
-                return false;
-            }
-
-            if i >= length - 1 {
-                return true;
+impl Input<Part2> {
+    /// Counts the crosses of two diagonal "MAS", each may be read either way.
+    fn parse(&self, highlight: bool) -> Result<(i32, Option<Highlight>)> {
+        let x_mas: Stencil = self.target[0].parse().map_err(|err: String| eyre!(err))?;
+        let matcher = StencilMatcher::new(x_mas).rotations(true);
+        let placements = matcher.find(&self.data);
+
+        let highlight = highlight.then(|| {
+            let mut highlight = Highlight::new(&self.data);
+            for placement in &placements {
+                highlight.mark(matcher.cells(placement));
             }
+            highlight
+        });
 
-            x = match checked_move(x, dx) {
-                Some(new) => new,
-                None => break,
-            };
-            y = match checked_move(y, dy) {
-                Some(new) => new,
-                None => break,
-            };
-
-            i += 1;
-        }
-
-        false
+        Ok((placements.len() as i32, highlight))
     }
 }
 
-impl Input<Part2> {
-    fn parse(&self) -> Result<i32> {
-        self.loop_through_input(|char| char != 'M' && char != 'S', Self::check_at_m)
-    }
-
-    fn check_at_m(&self, position: (usize, usize)) -> Result<i32> {
-        // Relevant:
-        // (x,y)    | ---------- | (x+2, y)   |
-        //  ------- | (x+1, y+1) | ---------- |
-        // (x, y+2) | ---------- | (x+2, y+2) |
-        let (x, y) = position;
-
-        let top_left: char = match self.get_at_position((x, y)) {
-            Some(char) => char,
-            None => return Ok(0),
-        };
-        let top_right: char = match self.get_at_position((x + 2, y)) {
-            Some(char) => char,
-            None => return Ok(0),
+/// Fuzz target: any input is either rejected by both parts or searched the same way
+/// every time.
+pub fn fuzz_input(data: &[u8]) {
+    deterministic(data, |data| {
+        let text = String::from_utf8_lossy(data);
+        let lines = || text.lines().map(str::to_string);
+        let part_1 = Input::<Part1>::default().parse_lines(lines());
+        let part_2 = Input::<Part2>::default().parse_lines(lines());
+        let (Ok(part_1), Ok(part_2)) = (part_1, part_2) else {
+            return None;
         };
-        let middle_middle: char = match self.get_at_position((x + 1, y + 1)) {
-            Some(char) => char,
-            None => return Ok(0),
-        };
-        let bottom_left: char = match self.get_at_position((x, y + 2)) {
-            Some(char) => char,
-            None => return Ok(0),
-        };
-        let bottom_right: char = match self.get_at_position((x + 2, y + 2)) {
-            Some(char) => char,
-            None => return Ok(0),
-        };
-
-        // A in the middle
-        if middle_middle != 'A' {
-            return Ok(0);
-        }
-
-        // Top right needs to be checked seperatly
-        if top_right != 'S' && top_right != 'M' {
-            return Ok(0);
-        }
-
-        // If top left is M, bottom right needs to be S
-        if top_left == 'S' && bottom_right != 'M' {
-            return Ok(0);
-        }
-
-        // If top left is S, bottom right needs to be M
-        if top_left == 'M' && bottom_right != 'S' {
-            return Ok(0);
-        }
-
-        // If top right is M, bottom left needs to be S
-        if top_right == 'S' && bottom_left != 'M' {
-            return Ok(0);
-        }
-
-        // If top right is S, bottom left needs to be M
-        if top_right == 'M' && bottom_left != 'S' {
-            return Ok(0);
-        }
-
-        Ok(1)
-    }
+        Some((
+            part_1.parse(false).unwrap().0,
+            part_2.parse(false).unwrap().0,
+        ))
+    });
 }
 
 impl Default for Input<Part1> {
     fn default() -> Self {
         Input {
             data: vec![],
-            target: vec![vec!['X', 'M', 'A', 'S']],
+            target: vec!["XMAS".to_string()],
             _marker: PhantomData,
         }
     }
@@ -209,16 +173,164 @@ impl Default for Input<Part2> {
     fn default() -> Self {
         Input {
             data: vec![],
-            target: vec![],
+            target: vec!["M.S\n.A.\nM.S".to_string()],
             _marker: PhantomData,
         }
     }
 }
 
-fn checked_move(origin: usize, offset: i8) -> Option<usize> {
-    if offset > 0 {
-        origin.checked_add(offset as usize)
-    } else {
-        origin.checked_sub((-offset) as usize)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzing::run_bounded;
+    use crate::generate::{Generator, LetterGrid};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use proptest::sample::select;
+
+    const EXAMPLE: &str = "\
+MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
+
+    fn input<Part>(mut input: Input<Part>, grid: &[Vec<char>]) -> Input<Part> {
+        for line in grid {
+            input.add_line(line.clone()).unwrap();
+        }
+        input
+    }
+
+    fn count_part_1(grid: &[Vec<char>]) -> i32 {
+        input(Input::<Part1>::default(), grid)
+            .parse(false)
+            .unwrap()
+            .0
+    }
+
+    fn count_part_2(grid: &[Vec<char>]) -> i32 {
+        input(Input::<Part2>::default(), grid)
+            .parse(false)
+            .unwrap()
+            .0
+    }
+
+    /// Reads "XMAS" from every cell in every direction.
+    fn naive_part_1(grid: &[Vec<char>]) -> i32 {
+        let letter = |x: isize, y: isize| {
+            let row = grid.get(usize::try_from(y).ok()?)?;
+            row.get(usize::try_from(x).ok()?).copied()
+        };
+
+        let mut count = 0;
+        for y in 0..grid.len() as isize {
+            for x in 0..grid[y as usize].len() as isize {
+                for (dx, dy) in [
+                    (1, 0),
+                    (1, 1),
+                    (0, 1),
+                    (-1, 1),
+                    (-1, 0),
+                    (-1, -1),
+                    (0, -1),
+                    (1, -1),
+                ] {
+                    let word: Option<String> =
+                        (0..4).map(|i| letter(x + dx * i, y + dy * i)).collect();
+                    if word.as_deref() == Some("XMAS") {
+                        count += 1;
+                    }
+                }
+            }
+        }
+        count
+    }
+
+    /// Checks both diagonals through every "A".
+    fn naive_part_2(grid: &[Vec<char>]) -> i32 {
+        let mut count = 0;
+        for y in 1..grid.len().saturating_sub(1) {
+            for x in 1..grid[y].len().saturating_sub(1) {
+                let mas = |a: char, b: char| matches!((a, b), ('M', 'S') | ('S', 'M'));
+                if grid[y][x] == 'A'
+                    && mas(grid[y - 1][x - 1], grid[y + 1][x + 1])
+                    && mas(grid[y - 1][x + 1], grid[y + 1][x - 1])
+                {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    fn grids() -> impl Strategy<Value = Vec<Vec<char>>> {
+        (1..9usize, 1..9usize).prop_flat_map(|(width, height)| {
+            vec(vec(select(vec!['X', 'M', 'A', 'S']), width), height)
+        })
+    }
+
+    #[test]
+    fn example_parts() {
+        let grid: Vec<Vec<char>> = EXAMPLE.lines().map(|line| line.chars().collect()).collect();
+        assert_eq!(count_part_1(&grid), 18);
+        assert_eq!(count_part_2(&grid), 9);
+    }
+
+    #[test]
+    fn highlights_only_when_asked() {
+        let grid: Vec<Vec<char>> = EXAMPLE.lines().map(|line| line.chars().collect()).collect();
+        let part_1 = input(Input::<Part1>::default(), &grid);
+        let part_2 = input(Input::<Part2>::default(), &grid);
+        assert_eq!(part_1.parse(false).unwrap(), (18, None));
+        assert_eq!(part_2.parse(false).unwrap(), (9, None));
+
+        let (_, xmas) = part_1.parse(true).unwrap();
+        let (_, x_mas) = part_2.parse(true).unwrap();
+        // "XMAS" starts at (5, 0), the centre of the first X-MAS is at (2, 1).
+        assert_eq!(xmas.unwrap().hits(5, 0), 1);
+        assert_eq!(x_mas.unwrap().hits(2, 1), 1);
+    }
+
+    #[test]
+    fn ragged_rows() {
+        let lines = ["XMAS", "", "SAMX", "XMA"].map(str::to_string);
+        let err = Input::<Part1>::default()
+            .parse_lines(lines.into_iter())
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Row 3 has 3 letters, expected 4");
+    }
+
+    #[test]
+    fn fuzz_input_bounded() {
+        let grid = LetterGrid {
+            width: 20,
+            height: 20,
+            ..Default::default()
+        };
+        run_bounded(
+            &[
+                EXAMPLE.as_bytes().to_vec(),
+                b"XMAS\nXMA\n".to_vec(),
+                "É\u{301}MAS\nXMAS".as_bytes().to_vec(),
+                grid.generate(1).into_bytes(),
+            ],
+            fuzz_input,
+        );
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn matches_naive(grid in grids()) {
+            prop_assert_eq!(count_part_1(&grid), naive_part_1(&grid));
+            prop_assert_eq!(count_part_2(&grid), naive_part_2(&grid));
+        }
     }
 }
@@ -1,7 +1,9 @@
-use aoc2024::{print_day_title, print_part_solution, read_lines};
-use color_eyre::Result;
-use std::collections::VecDeque;
-use std::collections::{HashMap, HashSet};
+use crate::fuzzing::deterministic;
+use crate::{print_day_title, print_part_solution, read_lines, run_options};
+use color_eyre::{eyre::eyre, Result};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use tracing::{debug, info_span};
 
 #[allow(dead_code)]
 const TEST_INPUT: &str = "assets/test_input_day05";
@@ -10,9 +12,73 @@ const INPUT: &str = "assets/input_day05";
 type PageRules = HashMap<u32, Page>;
 type Updates = Vec<Update>;
 
-enum UpdateKind {
+/// Which updates an aggregation looks at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpdateKind {
     Correct,
     Incorrect,
+    All,
+}
+
+impl UpdateKind {
+    fn includes(&self, was_incorrect: bool) -> bool {
+        match self {
+            UpdateKind::Correct => !was_incorrect,
+            UpdateKind::Incorrect => was_incorrect,
+            UpdateKind::All => true,
+        }
+    }
+}
+
+/// Which page counts as the middle of an update with an even number of pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum EvenMiddle {
+    /// The left one of the two middle pages.
+    Lower,
+    /// The right one of the two middle pages.
+    Upper,
+    /// The two middle pages averaged, rounded down.
+    Mean,
+    /// The update is left out.
+    #[default]
+    Skip,
+}
+
+pub type Aggregate = Box<dyn Fn(&[u32]) -> Option<u32>>;
+
+/// Turns the pages of an update, in corrected order, into the number that is summed.
+pub enum Aggregation {
+    Middle(EvenMiddle),
+    First,
+    Last,
+    /// All pages added up, left out if that overflows.
+    Sum,
+    Custom(Aggregate),
+}
+
+impl Aggregation {
+    /// The number for the pages, `None` leaves the update out.
+    pub fn apply(&self, pages: &[u32]) -> Option<u32> {
+        let len = pages.len();
+        match self {
+            Aggregation::Middle(_) if len % 2 == 1 => Some(pages[len / 2]),
+            Aggregation::Middle(even) => {
+                let (lower, upper) = (*pages.get(len.checked_sub(2)? / 2)?, pages[len / 2]);
+                match even {
+                    EvenMiddle::Lower => Some(lower),
+                    EvenMiddle::Upper => Some(upper),
+                    EvenMiddle::Mean => Some(((lower as u64 + upper as u64) / 2) as u32),
+                    EvenMiddle::Skip => None,
+                }
+            }
+            Aggregation::First => pages.first().copied(),
+            Aggregation::Last => pages.last().copied(),
+            Aggregation::Sum => pages
+                .iter()
+                .try_fold(0u32, |sum, &page| sum.checked_add(page)),
+            Aggregation::Custom(aggregate) => aggregate(pages),
+        }
+    }
 }
 
 pub fn main() -> Result<()> {
@@ -20,109 +86,498 @@ pub fn main() -> Result<()> {
 
     let (page_rules, updates) = read_input(INPUT)?;
 
+    let options = run_options();
+    let middle = Aggregation::Middle(options.even_middle);
+
     // Part 1
-    let sum: u32 = updates
-        .iter()
-        .filter_map(|update| update.parse_with_rules(&page_rules, UpdateKind::Correct))
-        .sum();
+    let sum = info_span!("part", part = 1)
+        .in_scope(|| sum_updates(&updates, &page_rules, UpdateKind::Correct, &middle));
     print_part_solution(1, "Sum of correct middle elements", sum);
 
     // Part 2
-    let sum: u32 = updates
-        .iter()
-        .filter_map(|update| update.parse_with_rules(&page_rules, UpdateKind::Incorrect))
-        .sum();
+    let sum = info_span!("part", part = 2)
+        .in_scope(|| sum_updates(&updates, &page_rules, UpdateKind::Incorrect, &middle));
     print_part_solution(2, "Sum of incorrect middle elements", sum);
 
+    if options.explain || options.explain_json.is_some() {
+        let explanations: Vec<Explanation> = updates
+            .iter()
+            .enumerate()
+            .map(|(index, update)| Explanation::new(index, update, &page_rules))
+            .filter(|explanation| !explanation.violations.is_empty())
+            .collect();
+
+        if options.explain {
+            for explanation in &explanations {
+                println!();
+                print!("{}", explanation);
+            }
+        }
+        if let Some(path) = &options.explain_json {
+            let json: Vec<String> = explanations.iter().map(Explanation::to_json).collect();
+            std::fs::write(path, format!("[\n{}\n]\n", json.join(",\n")))?;
+        }
+    }
+
+    if let Some(path) = &options.dot {
+        let update = match options.dot_update {
+            Some(number) => Some(
+                updates
+                    .get(number.wrapping_sub(1))
+                    .ok_or_else(|| eyre!("There is no update {}", number))?,
+            ),
+            None => None,
+        };
+        std::fs::write(path, to_dot(&page_rules, update))?;
+    }
+
     Ok(())
 }
 
+fn sum_updates(
+    updates: &[Update],
+    page_rules: &PageRules,
+    update_kind: UpdateKind,
+    aggregation: &Aggregation,
+) -> u64 {
+    updates
+        .iter()
+        .filter_map(|update| update.parse_with_rules(page_rules, update_kind, aggregation))
+        .map(u64::from)
+        .sum()
+}
+
+/// Reads rules and updates like the puzzle input and sums what `aggregation` makes of every
+/// update of the given kind, in corrected order.
+///
+/// ```
+/// use aoc2024::day05::{sum_input, Aggregation, UpdateKind};
+///
+/// let input = ["47|53", "", "53,47,61", "47,61,53"].map(str::to_string);
+/// let sum = sum_input(input.into_iter(), UpdateKind::All, &Aggregation::First).unwrap();
+/// assert_eq!(sum, 47 + 47);
+/// ```
+pub fn sum_input(
+    lines: impl Iterator<Item = String>,
+    update_kind: UpdateKind,
+    aggregation: &Aggregation,
+) -> Result<u64> {
+    let (page_rules, updates) = parse_input(lines)?;
+    Ok(sum_updates(&updates, &page_rules, update_kind, aggregation))
+}
+
 fn read_input(file: &str) -> Result<(PageRules, Updates)> {
     let lines = read_lines(file)?;
+    parse_input(lines.map_while(Result::ok))
+}
 
+/// Reads the rules up to the first empty line and the updates after it.
+fn parse_input(lines: impl Iterator<Item = String>) -> Result<(PageRules, Updates)> {
     let mut page_rules: HashMap<u32, Page> = HashMap::default();
     let mut updates: Vec<Update> = Vec::default();
 
     let mut line_break_occured = false;
-    lines.map_while(Result::ok).for_each(|line| {
+    for (index, line) in lines.enumerate() {
         if line.is_empty() {
             line_break_occured = true;
-            return;
+            continue;
         }
 
+        let pages = line
+            .split(if line_break_occured { ',' } else { '|' })
+            .map(|part| {
+                part.trim()
+                    .parse::<u32>()
+                    .map_err(|err| eyre!("Bad page {:?} on line {}: {}", part, index + 1, err))
+            })
+            .collect::<Result<Vec<u32>>>()?;
+
         if line_break_occured {
-            updates.push(Update::new(
-                line.split(",")
-                    .map(str::trim)
-                    .map(|part| part.parse::<u32>().expect("failed to parse update"))
-                    .collect(),
-            ))
+            updates.push(Update::new(pages));
+            continue;
+        }
+
+        let [before, after] = pages[..] else {
+            return Err(eyre!(
+                "Expected a rule like 47|53 on line {}, found {:?}",
+                index + 1,
+                line
+            ));
+        };
+        if let std::collections::hash_map::Entry::Occupied(mut entry) = page_rules.entry(before) {
+            let page = entry.get_mut();
+            page.add_dependant_page(after);
         } else {
-            let page_rule: Vec<u32> = line
-                .split("|")
-                .map(str::trim)
-                .map(|part| part.parse::<u32>().expect("failed to parse page_rule"))
-                .collect();
-
-            if let std::collections::hash_map::Entry::Occupied(mut entry) =
-                page_rules.entry(page_rule[0])
-            {
-                let page = entry.get_mut();
-                page.add_dependant_page(page_rule[1]);
-            } else {
-                page_rules.insert(
-                    page_rule[0],
-                    Page::with_dependant(page_rule[0], page_rule[1]),
-                );
-            }
+            page_rules.insert(before, Page::with_dependant(before, after));
         }
-    });
+    }
     Ok((page_rules, updates))
 }
 
-#[derive(Debug)]
-struct Update {
+/// Fuzz target: any input is either rejected or gives the same sums, explanations and
+/// graph every time.
+pub fn fuzz_input(data: &[u8]) {
+    deterministic(data, |data| {
+        let lines = String::from_utf8_lossy(data)
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let (page_rules, updates) = parse_input(lines.into_iter()).ok()?;
+
+        let middle = Aggregation::Middle(EvenMiddle::Lower);
+        let sums = [UpdateKind::Correct, UpdateKind::Incorrect, UpdateKind::All]
+            .map(|kind| sum_updates(&updates, &page_rules, kind, &middle));
+        let explanations: Vec<(String, String)> = updates
+            .iter()
+            .enumerate()
+            .map(|(index, update)| {
+                let explanation = Explanation::new(index, update, &page_rules);
+                (explanation.to_string(), explanation.to_json())
+            })
+            .collect();
+        let dot = to_dot(&page_rules, updates.first());
+        Some((sums, explanations, dot))
+    });
+}
+
+/// Whether a rule says `before` has to be printed before `after`.
+fn must_precede(page_rules: &PageRules, before: u32, after: u32) -> bool {
+    page_rules
+        .get(&before)
+        .is_some_and(|page| page.printed_before.contains(&after))
+}
+
+/// A rule `before|after` an update breaks by printing `after` first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Violation {
+    before: u32,
+    after: u32,
+    /// Positions of both pages in the update.
+    before_index: usize,
+    after_index: usize,
+}
+
+/// Takes `page` out at `from` and puts it back in at `to`, positions are counted in the
+/// update as it is before the move.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Move {
+    page: u32,
+    from: usize,
+    to: usize,
+}
+
+/// Why an update is incorrect and the fewest moves that correct it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Explanation {
+    /// Index of the update in the input.
+    update: usize,
     pages: Vec<u32>,
+    violations: Vec<Violation>,
+    /// `None` if the rules between the pages contain a cycle.
+    corrected: Option<Vec<u32>>,
+    moves: Vec<Move>,
 }
 
-impl Update {
-    fn new(pages: Vec<u32>) -> Self {
-        Self { pages }
+impl Explanation {
+    fn new(index: usize, update: &Update, page_rules: &PageRules) -> Self {
+        let pages = &update.pages;
+
+        let mut violations = vec![];
+        for (after_index, &after) in pages.iter().enumerate() {
+            for (before_index, &before) in pages.iter().enumerate().skip(after_index + 1) {
+                if must_precede(page_rules, before, after) {
+                    violations.push(Violation {
+                        before,
+                        after,
+                        before_index,
+                        after_index,
+                    });
+                }
+            }
+        }
+
+        let (corrected, moves) = if violations.is_empty() {
+            (Some(pages.clone()), vec![])
+        } else {
+            match minimal_moves(pages, page_rules) {
+                Some((corrected, moves)) => (Some(corrected), moves),
+                None => (None, vec![]),
+            }
+        };
+
+        Self {
+            update: index,
+            pages: pages.clone(),
+            violations,
+            corrected,
+            moves,
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let list = |pages: &[u32]| {
+            let pages: Vec<String> = pages.iter().map(u32::to_string).collect();
+            format!("[{}]", pages.join(","))
+        };
+        let violations: Vec<String> = self
+            .violations
+            .iter()
+            .map(|violation| {
+                format!(
+                    "{{\"rule\":[{},{}],\"positions\":[{},{}]}}",
+                    violation.before,
+                    violation.after,
+                    violation.before_index,
+                    violation.after_index
+                )
+            })
+            .collect();
+        let moves: Vec<String> = self
+            .moves
+            .iter()
+            .map(|step| {
+                format!(
+                    "{{\"page\":{},\"from\":{},\"to\":{}}}",
+                    step.page, step.from, step.to
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"index\":{},\"pages\":{},\"violations\":[{}],\"corrected\":{},\"moves\":[{}]}}",
+            self.update,
+            list(&self.pages),
+            violations.join(","),
+            self.corrected.as_deref().map_or("null".to_string(), list),
+            moves.join(",")
+        )
     }
+}
 
-    fn parse_with_rules(&self, page_rules: &PageRules, update_kind: UpdateKind) -> Option<u32> {
-        let mut printed_pages: VecDeque<u32> = VecDeque::new();
-        let mut update_was_incorrect = false;
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let list = |pages: &[u32]| {
+            pages
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        };
 
-        for page in &self.pages {
-            if let Some(rule) = page_rules.get(page) {
-                if let Some((index, _)) = printed_pages
-                    .iter()
-                    .enumerate()
-                    .find(|(_, item)| rule.printed_before.contains(item))
-                {
-                    update_was_incorrect = true;
-                    printed_pages.insert(index, *page);
-                } else {
-                    printed_pages.push_back(*page);
+        writeln!(f, "Update {}: {}", self.update + 1, list(&self.pages))?;
+        for violation in &self.violations {
+            writeln!(
+                f,
+                "  - breaks {}|{}: {} is at position {}, {} at {}",
+                violation.before,
+                violation.after,
+                violation.after,
+                violation.after_index,
+                violation.before,
+                violation.before_index
+            )?;
+        }
+
+        match &self.corrected {
+            Some(corrected) => {
+                for step in &self.moves {
+                    writeln!(
+                        f,
+                        "  ~ move {} from position {} to {}",
+                        step.page, step.from, step.to
+                    )?;
                 }
-            } else {
-                printed_pages.push_back(*page);
+                writeln!(f, "  = {}", list(corrected))
             }
+            None => writeln!(f, "  ! the rules between these pages contain a cycle"),
         }
+    }
+}
 
-        let update_len = printed_pages.len();
+/// Orders the pages so no rule is broken, keeping pages that are free to go anywhere in
+/// their original order. `None` if the rules between the pages form a cycle.
+fn sort_pages(pages: &[u32], page_rules: &PageRules) -> Option<Vec<u32>> {
+    let order = order_pages(pages.len(), |before, after| {
+        must_precede(page_rules, pages[before], pages[after])
+    })?;
+    Some(order.into_iter().map(|i| pages[i]).collect())
+}
+
+/// Orders the positions `0..len` so `precedes(before, after)` always holds for the order
+/// they come in, taking the first position that is free to go next. `None` on a cycle.
+fn order_pages(len: usize, precedes: impl Fn(usize, usize) -> bool) -> Option<Vec<usize>> {
+    let mut predecessors: Vec<usize> = (0..len)
+        .map(|i| (0..len).filter(|&other| precedes(other, i)).count())
+        .collect();
 
-        if update_len % 2 == 0 {
-            panic!("Even number of pages what's the middle?");
+    let mut placed = vec![false; len];
+    let mut sorted = Vec::with_capacity(len);
+    while sorted.len() < len {
+        let next = (0..len).find(|&i| !placed[i] && predecessors[i] == 0)?;
+        placed[next] = true;
+        sorted.push(next);
+        for (i, predecessors) in predecessors.iter_mut().enumerate() {
+            if precedes(next, i) {
+                *predecessors -= 1;
+            }
         }
+    }
+
+    Some(sorted)
+}
+
+/// The largest set of positions whose pages can stay where they are while the others move
+/// around them, `None` if the rules between the pages form a cycle.
+///
+/// Two pages can't both stay if the later one has to come before the earlier one, directly
+/// or through a chain of rules over the other pages. These conflicts are a partial order, so
+/// the pages that stay are a largest antichain of it, found through Dilworth's theorem: a
+/// maximum matching between the pages and the pages they conflict with gives a minimum
+/// vertex cover (König's theorem), and the pages covered on neither side can all stay.
+fn pages_to_keep(pages: &[u32], page_rules: &PageRules) -> Option<Vec<bool>> {
+    let len = pages.len();
+    // Transitive closure of the rules between the pages.
+    let mut reaches: Vec<Vec<bool>> = (0..len)
+        .map(|i| {
+            (0..len)
+                .map(|j| must_precede(page_rules, pages[i], pages[j]))
+                .collect()
+        })
+        .collect();
+    for via in 0..len {
+        let through = reaches[via].clone();
+        for row in reaches.iter_mut().filter(|row| row[via]) {
+            for (reach, &through) in row.iter_mut().zip(&through) {
+                *reach |= through;
+            }
+        }
+    }
+    if (0..len).any(|i| reaches[i][i]) {
+        return None;
+    }
+
+    // Whether the page at `i` has to come before the earlier page at `j`.
+    let conflicts = |i: usize, j: usize| j < i && reaches[i][j];
+
+    // Kuhn's augmenting paths, `matched[j]` is the later page matched to the page at `j`.
+    let mut matched: Vec<Option<usize>> = vec![None; len];
+    fn augment(
+        i: usize,
+        conflicts: &impl Fn(usize, usize) -> bool,
+        seen: &mut [bool],
+        matched: &mut [Option<usize>],
+    ) -> bool {
+        for j in 0..matched.len() {
+            if conflicts(i, j) && !seen[j] {
+                seen[j] = true;
+                if matched[j].is_none_or(|other| augment(other, conflicts, seen, matched)) {
+                    matched[j] = Some(i);
+                    return true;
+                }
+            }
+        }
+        false
+    }
+    let mut has_match = vec![false; len];
+    for (i, has_match) in has_match.iter_mut().enumerate() {
+        *has_match = augment(i, &conflicts, &mut vec![false; len], &mut matched);
+    }
+
+    // Alternating paths from the unmatched later pages. Starting on this side keeps the
+    // earlier of two pages that can't both stay.
+    let mut reached_later = vec![false; len];
+    let mut reached_earlier = vec![false; len];
+    let mut queue: Vec<usize> = (0..len).filter(|&i| !has_match[i]).collect();
+    for &i in &queue {
+        reached_later[i] = true;
+    }
+    while let Some(i) = queue.pop() {
+        for j in 0..len {
+            if !conflicts(i, j) || reached_earlier[j] {
+                continue;
+            }
+            reached_earlier[j] = true;
+            if let Some(other) = matched[j].filter(|&other| !reached_later[other]) {
+                reached_later[other] = true;
+                queue.push(other);
+            }
+        }
+    }
+
+    // The cover is the unreached later and the reached earlier pages.
+    Some(
+        (0..len)
+            .map(|i| reached_later[i] && !reached_earlier[i])
+            .collect(),
+    )
+}
+
+/// Corrects the pages with as few moves as possible, `None` if the rules between the pages
+/// form a cycle. Returns the corrected order and the moves leading to it.
+fn minimal_moves(pages: &[u32], page_rules: &PageRules) -> Option<(Vec<u32>, Vec<Move>)> {
+    let mut kept = pages_to_keep(pages, page_rules)?;
+    let order = order_pages(pages.len(), |before, after| {
+        must_precede(page_rules, pages[before], pages[after])
+            || (before < after && kept[before] && kept[after])
+    })?;
+
+    let mut current: Vec<usize> = (0..pages.len()).collect();
+    let mut moves = vec![];
+    for (rank, &i) in order.iter().enumerate() {
+        if kept[i] {
+            continue;
+        }
+
+        let from = current.iter().position(|&other| other == i).unwrap_or(0);
+        current.remove(from);
+        // In front of the first placed page that follows it.
+        let to = order[rank + 1..]
+            .iter()
+            .filter(|&&later| kept[later])
+            .find_map(|&later| current.iter().position(|&other| other == later))
+            .unwrap_or(current.len());
+        current.insert(to, i);
+        kept[i] = true;
+        moves.push(Move {
+            page: pages[i],
+            from,
+            to,
+        });
+    }
+
+    Some((order.into_iter().map(|i| pages[i]).collect(), moves))
+}
+
+#[derive(Debug)]
+struct Update {
+    pages: Vec<u32>,
+}
+
+impl Update {
+    fn new(pages: Vec<u32>) -> Self {
+        Self { pages }
+    }
 
-        let should_return_some = match update_kind {
-            UpdateKind::Correct => !update_was_incorrect,
-            UpdateKind::Incorrect => update_was_incorrect,
+    /// Aggregates the pages in corrected order if the update is of the requested kind.
+    /// Pages whose rules form a cycle can't be put in order, those updates are left out.
+    fn parse_with_rules(
+        &self,
+        page_rules: &PageRules,
+        update_kind: UpdateKind,
+        aggregation: &Aggregation,
+    ) -> Option<u32> {
+        let Some(corrected) = sort_pages(&self.pages, page_rules) else {
+            debug!("Rules between the pages of {:?} form a cycle", self.pages);
+            return None;
         };
 
-        should_return_some.then(|| printed_pages[(update_len - 1) / 2])
+        // Sorting keeps pages in place unless a rule moves them.
+        let update_was_incorrect = corrected != self.pages;
+        if !update_kind.includes(update_was_incorrect) {
+            return None;
+        }
+
+        aggregation.apply(&corrected)
     }
 }
 
@@ -144,3 +599,608 @@ impl Page {
         self.printed_before.insert(number);
     }
 }
+
+/// The rules as a Graphviz graph with an edge `X -> Y` for every rule `X|Y`.
+///
+/// The pages of `update` are filled and the rules between them drawn solid, broken ones
+/// in red. Every cycle in the rules is boxed and one of its loops spelled out.
+fn to_dot(page_rules: &PageRules, update: Option<&Update>) -> String {
+    let mut nodes: Vec<u32> = page_rules
+        .values()
+        .flat_map(|page| page.printed_before.iter().copied())
+        .chain(page_rules.keys().copied())
+        .collect::<HashSet<u32>>()
+        .into_iter()
+        .collect();
+    nodes.sort_unstable();
+    let edges = |page: u32| -> Vec<u32> {
+        let mut after: Vec<u32> = page_rules
+            .get(&page)
+            .map(|page| page.printed_before.iter().copied().collect())
+            .unwrap_or_default();
+        after.sort_unstable();
+        after
+    };
+
+    let position =
+        |page: u32| update.and_then(|update| update.pages.iter().position(|&p| p == page));
+
+    let mut dot = String::from("digraph rules {\n    node [shape=circle, fontname=monospace];\n");
+    if let Some(update) = update {
+        dot.push_str(&format!(
+            "    label=\"update {}\";\n    labelloc=t;\n",
+            update
+                .pages
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        ));
+    }
+
+    for (index, cycle) in cycles(&nodes, &edges).iter().enumerate() {
+        let path: Vec<String> = cycle
+            .iter()
+            .chain(cycle.first())
+            .map(u32::to_string)
+            .collect();
+        dot.push_str(&format!(
+            "    subgraph cluster_cycle_{} {{\n        label=\"cycle: {}\";\n        color=orange;\n        fontcolor=orange;\n",
+            index,
+            path.join(" -> ")
+        ));
+        for page in component_of(cycle[0], &nodes, &edges) {
+            dot.push_str(&format!("        {};\n", page));
+        }
+        dot.push_str("    }\n");
+    }
+
+    for &page in &nodes {
+        match position(page) {
+            Some(index) => dot.push_str(&format!(
+                "    {} [style=filled, fillcolor=lightblue, xlabel=\"#{}\"];\n",
+                page, index
+            )),
+            None if update.is_some() => {
+                dot.push_str(&format!("    {} [color=gray, fontcolor=gray];\n", page))
+            }
+            None => dot.push_str(&format!("    {};\n", page)),
+        }
+    }
+
+    for &before in &nodes {
+        for after in edges(before) {
+            let attributes = match (update, position(before), position(after)) {
+                (_, Some(b), Some(a)) if a < b => " [color=red, penwidth=2]",
+                (_, Some(_), Some(_)) => " [penwidth=2]",
+                (Some(_), _, _) => " [color=gray]",
+                (None, _, _) => "",
+            };
+            dot.push_str(&format!("    {} -> {}{};\n", before, after, attributes));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// One loop per strongly connected component with more than one page or a rule from its
+/// page to itself, as the pages along it starting from the smallest.
+fn cycles(nodes: &[u32], edges: &impl Fn(u32) -> Vec<u32>) -> Vec<Vec<u32>> {
+    let mut seen = HashSet::new();
+    let mut cycles = vec![];
+    for &start in nodes {
+        if seen.contains(&start) {
+            continue;
+        }
+        let component = component_of(start, nodes, edges);
+        seen.extend(component.iter().copied());
+        if component.len() < 2 && !edges(start).contains(&start) {
+            continue;
+        }
+
+        // Shortest way back to `start`, staying inside the component.
+        let start = component[0];
+        let mut came_from: HashMap<u32, u32> = HashMap::new();
+        let mut queue = VecDeque::from([start]);
+        'search: while let Some(page) = queue.pop_front() {
+            for next in edges(page) {
+                if next == start {
+                    let mut cycle = vec![page];
+                    while let Some(&previous) = came_from.get(cycle.last().unwrap_or(&start)) {
+                        cycle.push(previous);
+                    }
+                    cycle.reverse();
+                    cycles.push(cycle);
+                    break 'search;
+                }
+                if component.contains(&next) && !came_from.contains_key(&next) {
+                    came_from.insert(next, page);
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+    cycles
+}
+
+/// The sorted pages that can both reach and be reached from `page`.
+fn component_of(page: u32, nodes: &[u32], edges: &impl Fn(u32) -> Vec<u32>) -> Vec<u32> {
+    let reachable = |from: u32, forward: bool| {
+        let mut found = HashSet::from([from]);
+        let mut stack = vec![from];
+        while let Some(current) = stack.pop() {
+            let next: Vec<u32> = if forward {
+                edges(current)
+            } else {
+                nodes
+                    .iter()
+                    .copied()
+                    .filter(|&other| edges(other).contains(&current))
+                    .collect()
+            };
+            for next in next {
+                if found.insert(next) {
+                    stack.push(next);
+                }
+            }
+        }
+        found
+    };
+
+    let forward = reachable(page, true);
+    let mut component: Vec<u32> = reachable(page, false)
+        .intersection(&forward)
+        .copied()
+        .collect();
+    component.sort_unstable();
+    component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzing::run_bounded;
+    use crate::generate::{self, Generator};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    const EXAMPLE: &str = "\
+47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+
+75,47,61,53,29
+97,61,53,29,13
+75,29,13
+75,97,47,61,53
+61,13,29
+97,13,75,29,47";
+
+    fn example() -> (PageRules, Updates) {
+        parse_input(EXAMPLE.lines().map(str::to_string)).unwrap()
+    }
+
+    #[test]
+    fn correct_updates_have_no_explanation() {
+        let (page_rules, updates) = example();
+        let explanation = Explanation::new(0, &updates[0], &page_rules);
+        assert!(explanation.violations.is_empty());
+        assert!(explanation.moves.is_empty());
+        assert_eq!(explanation.corrected, Some(updates[0].pages.clone()));
+    }
+
+    #[test]
+    fn explains_violations() {
+        let (page_rules, updates) = example();
+
+        let explanation = Explanation::new(3, &updates[3], &page_rules);
+        assert_eq!(
+            explanation.violations,
+            vec![Violation {
+                before: 97,
+                after: 75,
+                before_index: 1,
+                after_index: 0,
+            }]
+        );
+        assert_eq!(explanation.corrected, Some(vec![97, 75, 47, 61, 53]));
+        assert_eq!(
+            explanation.moves,
+            vec![Move {
+                page: 97,
+                from: 1,
+                to: 0,
+            }]
+        );
+        assert_eq!(
+            explanation.to_json(),
+            "{\"index\":3,\"pages\":[75,97,47,61,53],\"violations\":[{\"rule\":[97,75],\"positions\":[1,0]}],\"corrected\":[97,75,47,61,53],\"moves\":[{\"page\":97,\"from\":1,\"to\":0}]}"
+        );
+
+        let explanation = Explanation::new(5, &updates[5], &page_rules);
+        assert_eq!(explanation.corrected, Some(vec![97, 75, 47, 29, 13]));
+        assert_eq!(explanation.violations.len(), 4);
+        assert_eq!(explanation.moves.len(), 2);
+    }
+
+    #[test]
+    fn moves_produce_the_corrected_order() {
+        let (page_rules, updates) = example();
+        for (index, update) in updates.iter().enumerate() {
+            let explanation = Explanation::new(index, update, &page_rules);
+            let mut pages = explanation.pages.clone();
+            for step in &explanation.moves {
+                assert_eq!(pages.remove(step.from), step.page);
+                pages.insert(step.to, step.page);
+            }
+            assert_eq!(Some(pages), explanation.corrected);
+        }
+    }
+
+    #[test]
+    fn moves_keep_the_most_pages() {
+        // Correcting along the first order the rules allow, 5,1,4,2,3,0, moves two pages.
+        let rules = ["3|0", "3|1", "4|0", "5|1", "5|4"];
+        let (page_rules, _) = parse_input(rules.map(str::to_string).into_iter()).unwrap();
+        let explanation = Explanation::new(0, &Update::new(vec![5, 1, 4, 2, 0, 3]), &page_rules);
+        assert_eq!(explanation.corrected, Some(vec![5, 3, 1, 4, 2, 0]));
+        assert_eq!(
+            explanation.moves,
+            vec![Move {
+                page: 3,
+                from: 5,
+                to: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn cycles_cannot_be_corrected() {
+        let (page_rules, _) =
+            parse_input(["1|2", "2|3", "3|1"].map(str::to_string).into_iter()).unwrap();
+        let explanation = Explanation::new(0, &Update::new(vec![3, 2, 1]), &page_rules);
+        assert_eq!(explanation.violations.len(), 2);
+        assert_eq!(explanation.corrected, None);
+        assert!(explanation.to_string().contains("cycle"));
+    }
+
+    #[test]
+    fn dot_export() {
+        let (page_rules, updates) = example();
+
+        let dot = to_dot(&page_rules, None);
+        assert!(dot.starts_with("digraph rules {"));
+        assert!(dot.contains("    97 -> 75;\n"));
+        assert!(!dot.contains("cycle"));
+
+        let dot = to_dot(&page_rules, Some(&updates[3]));
+        assert!(dot.contains("    97 -> 75 [color=red, penwidth=2];\n"));
+        assert!(dot.contains("    75 -> 47 [penwidth=2];\n"));
+        assert!(dot.contains("    97 -> 13 [color=gray];\n"));
+        assert!(dot.contains("    97 [style=filled, fillcolor=lightblue, xlabel=\"#1\"];\n"));
+    }
+
+    #[test]
+    fn dot_cycles() {
+        let rules = ["1|2", "2|3", "3|1", "3|4", "4|5", "5|4", "6|1"];
+        let (page_rules, _) = parse_input(rules.map(str::to_string).into_iter()).unwrap();
+
+        let dot = to_dot(&page_rules, None);
+        assert!(dot.contains("label=\"cycle: 1 -> 2 -> 3 -> 1\""));
+        assert!(dot.contains("label=\"cycle: 4 -> 5 -> 4\""));
+        assert_eq!(dot.matches("subgraph cluster_cycle").count(), 2);
+    }
+
+    #[test]
+    fn self_loops_are_cycles() {
+        let rules = ["1|2", "2|2", "2|3"];
+        let (page_rules, _) = parse_input(rules.map(str::to_string).into_iter()).unwrap();
+
+        let dot = to_dot(&page_rules, None);
+        assert!(dot.contains("label=\"cycle: 2 -> 2\""));
+        assert!(dot.contains("        2;\n    }\n"));
+        assert_eq!(dot.matches("subgraph cluster_cycle").count(), 1);
+    }
+
+    #[test]
+    fn bad_input() {
+        let parse = |lines: &[&str]| {
+            parse_input(lines.iter().map(|line| line.to_string()))
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            parse(&["1|2", "3|"]),
+            "Bad page \"\" on line 2: cannot parse integer from empty string"
+        );
+        assert_eq!(
+            parse(&["1|2|3"]),
+            "Expected a rule like 47|53 on line 1, found \"1|2|3\""
+        );
+        assert_eq!(
+            parse(&["1|2", "", "1,x"]),
+            "Bad page \"x\" on line 3: invalid digit found in string"
+        );
+    }
+
+    #[test]
+    fn fuzz_input_bounded() {
+        let rules = generate::PageRules {
+            pages: 12,
+            updates: 10,
+            min_update: 1,
+            max_update: 9,
+            ..Default::default()
+        };
+        run_bounded(
+            &[
+                EXAMPLE.as_bytes().to_vec(),
+                b"4294967295|1\n\n4294967295,4294967295,1\n".to_vec(),
+                rules.generate(1).into_bytes(),
+            ],
+            fuzz_input,
+        );
+    }
+
+    #[test]
+    fn example_parts() {
+        let (page_rules, updates) = example();
+        let middle = Aggregation::Middle(EvenMiddle::Skip);
+
+        let sum = |kind| sum_updates(&updates, &page_rules, kind, &middle);
+        assert_eq!(sum(UpdateKind::Correct), 143);
+        assert_eq!(sum(UpdateKind::Incorrect), 123);
+        assert_eq!(sum(UpdateKind::All), 143 + 123);
+    }
+
+    #[test]
+    fn partial_rules_are_followed() {
+        // Inserting 12 before 11 alone would leave it behind 10.
+        let (page_rules, _) =
+            parse_input(["10|12", "12|11"].map(str::to_string).into_iter()).unwrap();
+        let update = Update::new(vec![11, 10, 12]);
+        let first =
+            update.parse_with_rules(&page_rules, UpdateKind::Incorrect, &Aggregation::First);
+        assert_eq!(first, Some(10));
+    }
+
+    #[test]
+    fn aggregations() {
+        let (page_rules, updates) = example();
+        let sum = |aggregation: Aggregation| {
+            sum_updates(&updates, &page_rules, UpdateKind::Incorrect, &aggregation)
+        };
+
+        // Corrected: 97,75,47,61,53 / 61,29,13 / 97,75,47,29,13
+        assert_eq!(sum(Aggregation::First), 97 + 61 + 97);
+        assert_eq!(sum(Aggregation::Last), 53 + 13 + 13);
+        assert_eq!(sum(Aggregation::Sum), 333 + 103 + 261);
+        assert_eq!(
+            sum(Aggregation::Custom(Box::new(|pages| {
+                (pages.len() > 3).then_some(pages.len() as u32)
+            }))),
+            10
+        );
+    }
+
+    #[test]
+    fn even_middles() {
+        let pages = [1, 2, 4, 8];
+        let middle = |even| Aggregation::Middle(even).apply(&pages);
+        assert_eq!(middle(EvenMiddle::Lower), Some(2));
+        assert_eq!(middle(EvenMiddle::Upper), Some(4));
+        assert_eq!(middle(EvenMiddle::Mean), Some(3));
+        assert_eq!(middle(EvenMiddle::Skip), None);
+
+        assert_eq!(Aggregation::Middle(EvenMiddle::Lower).apply(&[]), None);
+        assert_eq!(Aggregation::Middle(EvenMiddle::Lower).apply(&[5]), Some(5));
+    }
+
+    /// Pages `10..10 + n` whose rules all follow a hidden order, a chosen subset of the
+    /// rules and odd length updates.
+    #[derive(Debug, Clone)]
+    struct Puzzle {
+        order: Vec<u32>,
+        rules: Vec<(u32, u32)>,
+        updates: Vec<Vec<u32>>,
+    }
+
+    impl Puzzle {
+        fn parse(&self) -> (PageRules, Updates) {
+            let rules = self
+                .rules
+                .iter()
+                .map(|(before, after)| format!("{}|{}", before, after));
+            let updates = self.updates.iter().map(|update| {
+                let pages: Vec<String> = update.iter().map(u32::to_string).collect();
+                pages.join(",")
+            });
+            parse_input(rules.chain([String::new()]).chain(updates)).unwrap()
+        }
+
+        /// Whether no two pages of the update are printed against a rule.
+        fn naive_correct(&self, pages: &[u32]) -> bool {
+            (0..pages.len())
+                .all(|i| (i + 1..pages.len()).all(|j| !self.rules.contains(&(pages[j], pages[i]))))
+        }
+
+        /// Tries every valid order: a page has to move unless it is on a longest run of
+        /// pages that are already in that order.
+        fn naive_moves(&self, pages: &[u32]) -> usize {
+            fn orders(pages: &[u32]) -> Vec<Vec<u32>> {
+                if pages.is_empty() {
+                    return vec![vec![]];
+                }
+                (0..pages.len())
+                    .flat_map(|i| {
+                        let mut rest = pages.to_vec();
+                        let first = rest.remove(i);
+                        orders(&rest).into_iter().map(move |mut order| {
+                            order.insert(0, first);
+                            order
+                        })
+                    })
+                    .collect()
+            }
+
+            let longest_run = |order: &[u32]| {
+                let ranks: Vec<usize> = pages
+                    .iter()
+                    .map(|page| order.iter().position(|other| other == page).unwrap())
+                    .collect();
+                let mut longest = vec![1; ranks.len()];
+                for i in 0..ranks.len() {
+                    for j in 0..i {
+                        if ranks[j] < ranks[i] {
+                            longest[i] = longest[i].max(longest[j] + 1);
+                        }
+                    }
+                }
+                longest.into_iter().max().unwrap_or(0)
+            };
+
+            let kept = orders(pages)
+                .iter()
+                .filter(|order| self.naive_correct(order))
+                .map(|order| longest_run(order))
+                .max()
+                .unwrap_or(0);
+            pages.len() - kept
+        }
+
+        /// Orders the pages like the hidden order, which is the only order following all
+        /// rules when every pair of pages has one.
+        fn naive_corrected(&self, pages: &[u32]) -> Vec<u32> {
+            let mut pages = pages.to_vec();
+            pages.sort_by_key(|page| self.order.iter().position(|other| other == page));
+            pages
+        }
+    }
+
+    fn puzzles(complete: bool) -> impl Strategy<Value = Puzzle> {
+        (1..8usize).prop_flat_map(move |pages| {
+            let order = Just((10..10 + pages as u32).collect::<Vec<_>>()).prop_shuffle();
+            let included = vec(
+                if complete {
+                    Just(true).boxed()
+                } else {
+                    any::<bool>().boxed()
+                },
+                pages * (pages - 1) / 2,
+            );
+            let update = (
+                Just((10..10 + pages as u32).collect::<Vec<_>>()).prop_shuffle(),
+                0..pages.div_ceil(2),
+            )
+                .prop_map(|(pages, half)| pages[..2 * half + 1].to_vec());
+
+            (order, included, vec(update, 0..8)).prop_map(|(order, included, updates)| {
+                let pairs = (0..order.len())
+                    .flat_map(|i| (i + 1..order.len()).map(move |j| (i, j)))
+                    .collect::<Vec<_>>();
+                let rules = pairs
+                    .into_iter()
+                    .zip(included)
+                    .filter(|(_, included)| *included)
+                    .map(|((i, j), _)| (order[i], order[j]))
+                    .collect();
+                Puzzle {
+                    order,
+                    rules,
+                    updates,
+                }
+            })
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn correct_updates_match_naive(puzzle in puzzles(false)) {
+            let (page_rules, updates) = puzzle.parse();
+            let middle = Aggregation::Middle(EvenMiddle::Skip);
+            let naive: u64 = puzzle
+                .updates
+                .iter()
+                .filter(|pages| puzzle.naive_correct(pages))
+                .map(|pages| u64::from(pages[pages.len() / 2]))
+                .sum();
+            prop_assert_eq!(
+                sum_updates(&updates, &page_rules, UpdateKind::Correct, &middle),
+                naive
+            );
+        }
+
+        #[test]
+        fn corrections_follow_every_rule(puzzle in puzzles(false)) {
+            let (page_rules, updates) = puzzle.parse();
+            for (update, pages) in updates.iter().zip(&puzzle.updates) {
+                let checked = puzzle.clone();
+                let expected = pages.clone();
+                let valid = Aggregation::Custom(Box::new(move |corrected| {
+                    let mut sorted = corrected.to_vec();
+                    let mut expected = expected.clone();
+                    sorted.sort_unstable();
+                    expected.sort_unstable();
+                    Some((sorted == expected && checked.naive_correct(corrected)) as u32)
+                }));
+                prop_assert_eq!(
+                    update.parse_with_rules(&page_rules, UpdateKind::All, &valid),
+                    Some(1),
+                    "{:?}",
+                    pages
+                );
+            }
+        }
+
+        #[test]
+        fn moves_are_minimal(puzzle in puzzles(false)) {
+            let (page_rules, updates) = puzzle.parse();
+            for (index, update) in updates.iter().enumerate() {
+                let explanation = Explanation::new(index, update, &page_rules);
+                prop_assert_eq!(
+                    explanation.moves.len(),
+                    puzzle.naive_moves(&update.pages),
+                    "{:?}",
+                    update.pages
+                );
+            }
+        }
+
+        #[test]
+        fn incorrect_updates_match_naive(puzzle in puzzles(true)) {
+            let (page_rules, updates) = puzzle.parse();
+            let middle = Aggregation::Middle(EvenMiddle::Skip);
+            let naive: u64 = puzzle
+                .updates
+                .iter()
+                .filter(|pages| !puzzle.naive_correct(pages))
+                .map(|pages| u64::from(puzzle.naive_corrected(pages)[pages.len() / 2]))
+                .sum();
+            prop_assert_eq!(
+                sum_updates(&updates, &page_rules, UpdateKind::Incorrect, &middle),
+                naive
+            );
+        }
+    }
+}
@@ -1,9 +1,14 @@
-use aoc2024::{print_day_title, print_part_solution, read_lines};
+use crate::collections::{FastHashMap, FastHashSet, PointSet};
+use crate::fuzzing::deterministic;
+use crate::{print_day_title, print_part_solution, read_lines};
+use color_eyre::eyre::{eyre, Report};
 use color_eyre::Result;
-use console::{style, Term};
-use std::collections::{HashMap, HashSet};
 use std::fmt;
+use std::str::FromStr;
+use std::thread;
+use tracing::{debug, info_span, trace};
 
+#[allow(dead_code)]
 const TEST_INPUT: &str = "assets/test_input_day06";
 const INPUT: &str = "assets/input_day06";
 
@@ -11,379 +16,563 @@ pub fn main() -> Result<()> {
     print_day_title(6);
 
     let (mut map, guard) = read_input(INPUT)?;
-    let fields = move_guard(&mut map, guard)?;
-    print_part_solution(1, "The guard visited unique fields:", fields);
+    let start = guard.clone();
+    let part_1 = info_span!("part", part = 1).entered();
+    #[cfg(any(feature = "viz", feature = "export"))]
+    let trajectory = {
+        let mut observer = render::Observer::from_run_options()?;
+        let trajectory = move_guard(&mut map, guard, |map, guard| observer.step(map, guard))?;
+        observer.finish(&map)?;
+        trajectory
+    };
+    #[cfg(not(any(feature = "viz", feature = "export")))]
+    let trajectory = move_guard(&mut map, guard, |_, _| Ok(()))?;
+    part_1.exit();
+    print_part_solution(1, "The guard visited unique fields:", map.visited.len());
+
+    if let Some(path) = &crate::run_options().record {
+        std::fs::write(path, format!("{}\n", trajectory))?;
+    }
 
-    is_valid_obstacle(&Position { x: 3, y: 8 }, &map);
-    print_part_solution(2, "TBD", "-");
+    let obstructions =
+        info_span!("part", part = 2).in_scope(|| count_loop_obstructions(&map, &start));
+    print_part_solution(2, "Positions causing a loop:", obstructions);
 
     Ok(())
 }
 
-fn move_guard(map: &mut Map, mut guard: Guard) -> Result<usize> {
+/// Walks the guard until it leaves the map, calling `on_step` before every step.
+/// Fails if the guard turns where it already turned facing the same way, it would walk in
+/// a loop forever.
+pub fn move_guard<F>(map: &mut Map, mut guard: Guard, mut on_step: F) -> Result<Trajectory>
+where
+    F: FnMut(&Map, &Guard) -> Result<()>,
+{
+    let mut trajectory = Trajectory::default();
+    let mut kind = StepKind::Start;
+    let mut turns: FastHashSet<(Position, Direction)> = FastHashSet::default();
+
     loop {
-        // map.print_state(&guard);
-        println!("");
-        println!("");
-        map.visited.insert(guard.position.clone());
-        map.visited_directional.insert(guard.position.clone(), guard.direction.clone());
+        on_step(map, &guard)?;
+        map.visited.insert(guard.position.x, guard.position.y);
+        trajectory.push(&guard, kind);
 
         let position_ahead = guard.look_ahead();
-        // println!("Current position is: {}", guard.position);
-        // println!("Next position is: {}", position_ahead);
-        //
+        trace!(
+            "Guard at {} facing {:?}, next position is {}",
+            guard.position,
+            guard.direction,
+            position_ahead
+        );
+
         if map.is_out_of_bounds(&position_ahead) {
-            // println!("Next position out of bounds");
+            trace!("Next position is out of bounds");
             break;
         }
 
         if map.is_blocked(&position_ahead) {
-            // println!("Next is blocked, turning");
+            trace!("Next position is blocked, turning");
             guard.direction = guard.direction.turn();
-            map.add_turn(guard.position.clone());
+            if !turns.insert((guard.position.clone(), guard.direction.clone())) {
+                return Err(eyre!(
+                    "The guard walks in a loop, it turned at {} before",
+                    guard.position
+                ));
+            }
+            kind = StepKind::Turn;
             continue;
         }
 
-        // println!("Moving to next");
         guard.move_ahead();
-        // std::thread::sleep(std::time::Duration::from_millis(50));
+        kind = StepKind::Move;
     }
 
-    Ok(map.visited.len())
+    Ok(trajectory)
 }
 
-fn is_valid_obstacle(position: &Position, map: &Map) -> bool {
-    let mut possible_intersections: HashSet<(Position, Position, Position)> = HashSet::new();
-    for turn in &map.turns {
-        // find turn intersections:
-        let same_row = map.y_turns.get(&turn.y).unwrap();
-        let same_col = map.x_turns.get(&turn.x).unwrap();
-
-        println!("Same Row: {}", same_row.len());
-        println!("Same Col: {}", same_col.len());
-        for turn_2 in same_row {
-            if turn != turn_2 {
-                for turn_3 in same_col {
-                    if turn != turn_3 && turn_2 != turn_3 {
-                        possible_intersections.insert((turn.clone(), turn_2.clone(), turn_3.clone()));
-                    }
-                }
-            }
-        }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepKind {
+    Start,
+    Move,
+    Turn,
+}
+
+/// State of the guard after a step, a turn keeps the position but changes the direction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub position: Position,
+    pub direction: Direction,
+    pub kind: StepKind,
+}
+
+/// The recorded walk of the guard, the index of a step is its time.
+///
+/// Its text form is the start position followed by one run per straight line,
+/// e.g. `4,6 U5 R4 D5`. Every run after the first one starts with a turn.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trajectory {
+    steps: Vec<Step>,
+}
 
-        // Ensure there's at least one other turn in the same row and column
-        // if same_row.is_some() && same_col.is_some() {
-        //     possible_intersections.insert(turn.clone());
-        // }
+impl Trajectory {
+    /// Walks the guard across a map in the puzzle's text form.
+    pub fn from_map(map: &str) -> Result<Self> {
+        let (mut map, guard) = parse_input(map.lines().map(str::to_string))?;
+        move_guard(&mut map, guard, |_, _| Ok(()))
     }
 
-    println!("Possible intersections: {}", possible_intersections.len());
+    fn push(&mut self, guard: &Guard, kind: StepKind) {
+        self.steps.push(Step {
+            position: guard.position.clone(),
+            direction: guard.direction.clone(),
+            kind,
+        });
+    }
 
-    let mut sum = 0;
-    for intersection in possible_intersections {
-        let (turn1, turn2, turn3) = intersection;
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Time at which the guard first stood on `position`.
+    pub fn first_visit(&self, position: &Position) -> Option<usize> {
+        self.steps
+            .iter()
+            .position(|step| step.position == *position)
+    }
+
+    /// Every direction the guard faced on `position`, in the order they first occurred.
+    pub fn directions_at(&self, position: &Position) -> Vec<Direction> {
+        let mut directions = vec![];
+        for step in self.steps.iter().filter(|step| step.position == *position) {
+            if !directions.contains(&step.direction) {
+                directions.push(step.direction.clone());
+            }
+        }
+        directions
+    }
+
+    /// Times at which the guard turned.
+    pub fn turns(&self) -> impl Iterator<Item = usize> + '_ {
+        self.steps
+            .iter()
+            .enumerate()
+            .filter(|(_, step)| step.kind == StepKind::Turn)
+            .map(|(time, _)| time)
+    }
 
-        if let Some(turn4) = find_fourth_turn(&turn1, &turn2, &turn3) {
-        println!("4th turn needs to be done at: {:#?}", turn4);
+    /// Steps from the `from`-th to the `to`-th turn, both turns included.
+    pub fn segment(&self, from: usize, to: usize) -> Option<&[Step]> {
+        if from > to {
+            return None;
+        }
+        let start = self.turns().nth(from)?;
+        let end = self.turns().nth(to)?;
+        Some(&self.steps[start..=end])
+    }
+}
 
-        if let Some(obstacle) = calculate_obstacle_position(&turn4, &map.visited_directional) {
-                if !map.is_out_of_bounds(&obstacle) && obstacle != map.start_position {
-                    sum += 1;
+impl fmt::Display for Trajectory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(start) = self.steps.first() else {
+            return Ok(());
+        };
+        write!(f, "{},{}", start.position.x, start.position.y)?;
+
+        let mut direction = &start.direction;
+        let mut moves = 0;
+        for step in &self.steps[1..] {
+            match step.kind {
+                StepKind::Move => moves += 1,
+                StepKind::Turn | StepKind::Start => {
+                    write!(f, " {}{}", direction.letter(), moves)?;
+                    direction = &step.direction;
+                    moves = 0;
                 }
+            }
+        }
+        write!(f, " {}{}", direction.letter(), moves)
+    }
+}
 
+impl FromStr for Trajectory {
+    type Err = Report;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut parts = text.split_whitespace();
+        let (x, y) = parts
+            .next()
+            .and_then(|start| start.split_once(','))
+            .ok_or_else(|| eyre!("Trajectory is missing its start position"))?;
+        let mut guard = Guard {
+            position: Position {
+                x: x.parse()?,
+                y: y.parse()?,
+            },
+            direction: Direction::Up,
+        };
+
+        let mut trajectory = Trajectory::default();
+        for (i, run) in parts.enumerate() {
+            let mut chars = run.chars();
+            let direction = chars
+                .next()
+                .and_then(Direction::from_letter)
+                .ok_or_else(|| eyre!("Invalid run: {}", run))?;
+            let moves: usize = chars.as_str().parse()?;
+
+            if i == 0 {
+                guard.direction = direction;
+                trajectory.push(&guard, StepKind::Start);
+            } else if guard.direction.clone().turn() == direction {
+                guard.direction = direction;
+                trajectory.push(&guard, StepKind::Turn);
+            } else {
+                return Err(eyre!("Run {} does not follow a right turn", run));
             }
 
+            for _ in 0..moves {
+                guard.move_ahead();
+                trajectory.push(&guard, StepKind::Move);
+            }
         }
+
+        Ok(trajectory)
     }
-    println!("Sum: {}", sum);
+}
 
-    true
+/// Fuzz target: any input is either rejected or gives the same path and obstructions
+/// every time. Maps where the guard already walks in a loop are only checked for that,
+/// walking them would never end.
+pub fn fuzz_input(data: &[u8]) {
+    let _ = deterministic(data, |data| -> Result<_, String> {
+        let lines = String::from_utf8_lossy(data)
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>();
+        let (mut map, guard) = parse_input(lines.into_iter()).map_err(|err| err.to_string())?;
+
+        // An obstruction off the map is never in the guard's way.
+        let off_map = Position { x: -1, y: -1 };
+        let walked = move_guard(&mut map, guard.clone(), |_, _| Ok(()));
+        let looped = is_loop_with(&JumpTable::new(&map), guard.clone(), &off_map);
+        assert_eq!(walked.is_err(), looped, "{:?}", walked);
+        let Ok(trajectory) = walked else {
+            return Ok(None);
+        };
+        let text = trajectory.to_string();
+        assert_eq!(text.parse::<Trajectory>().unwrap(), trajectory);
+        let obstructions = count_loop_obstructions(&map, &guard);
+        Ok(Some((map.visited.len(), obstructions, text)))
+    });
 }
 
-fn find_fourth_turn(
-    turn1: &Position,
-    turn2: &Position,
-    turn3: &Position,
-) -> Option<Position> {
-    // Extract coordinates for the turns
-    let (x1, y1) = (turn1.x, turn1.y);
-    let (x2, y2) = (turn2.x, turn2.y);
-    let (x3, y3) = (turn3.x, turn3.y);
-
-    // Determine the possible x and y for the fourth turn
-    let x_candidates = [x1, x2, x3];
-    let y_candidates = [y1, y2, y3];
-
-    for &x in &x_candidates {
-        for &y in &y_candidates {
-            let fourth_turn = Position { x, y };
-
-            // Ensure it's not one of the three existing turns
-            if fourth_turn != *turn1 && fourth_turn != *turn2 && fourth_turn != *turn3 {
-                return Some(fourth_turn);
+/// Counts the cells on the guard's path where a new obstruction traps the guard in a loop.
+/// The candidates are split evenly over all available cores.
+///
+/// Expects `map` to already contain the path of the guard starting at `start`.
+pub fn count_loop_obstructions(map: &Map, start: &Guard) -> usize {
+    let jumps = JumpTable::new(map);
+    let candidates: Vec<Position> = map
+        .visited
+        .iter()
+        .map(|(x, y)| Position { x, y })
+        .filter(|position| *position != start.position)
+        .collect();
+
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+    let chunk_size = candidates.len().div_ceil(threads).max(1);
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = candidates
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let jumps = &jumps;
+                scope.spawn(move || {
+                    chunk
+                        .iter()
+                        .filter(|obstruction| is_loop_with(jumps, start.clone(), obstruction))
+                        .count()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("Loop search thread panicked"))
+            .sum()
+    })
+}
+
+/// Moves the guard from turn to turn with an additional obstruction, until it either
+/// leaves the map or turns at a position it already turned at facing the same direction.
+fn is_loop_with(jumps: &JumpTable, mut guard: Guard, obstruction: &Position) -> bool {
+    let mut seen: FastHashSet<(Position, Direction)> = FastHashSet::default();
+
+    loop {
+        let mut stop = jumps.stop(&guard.position, &guard.direction).cloned();
+
+        if let Some(distance) = guard.distance_ahead(obstruction) {
+            let before_stop = stop
+                .as_ref()
+                .is_none_or(|stop| distance <= guard.position.distance(stop));
+            if before_stop {
+                stop = Some(guard.position.moved(&guard.direction, distance - 1));
             }
         }
+
+        let Some(stop) = stop else {
+            return false;
+        };
+        guard.position = stop;
+        guard.direction = guard.direction.turn();
+
+        if !seen.insert((guard.position.clone(), guard.direction.clone())) {
+            trace!("Obstruction at {} causes a loop", obstruction);
+            return true;
+        }
     }
+}
 
-    None // No valid fourth turn found
+/// Where the guard stops in front of the next obstacle, for every cell and direction.
+/// `None` if the guard leaves the map instead.
+struct JumpTable {
+    width: usize,
+    stops: Vec<Option<Position>>,
 }
 
-fn calculate_obstacle_position(
-    fourth_turn: &Position,
-    visited_directional: &HashMap<Position, Direction>,
-) -> Option<Position> {
-    // Get the guard's direction at the fourth turn
-    let current_direction = visited_directional.get(fourth_turn)?;
-
-    // Calculate the position ahead based on the current direction
-    let position_ahead = match current_direction {
-        Direction::Up => Position {
-            x: fourth_turn.x,
-            y: fourth_turn.y + 1,
-        },
-        Direction::Right => Position {
-            x: fourth_turn.x + 1,
-            y: fourth_turn.y,
-        },
-        Direction::Down => Position {
-            x: fourth_turn.x,
-            y: fourth_turn.y - 1,
-        },
-        Direction::Left => Position {
-            x: fourth_turn.x - 1,
-            y: fourth_turn.y,
-        },
-    };
+impl JumpTable {
+    fn new(map: &Map) -> Self {
+        let sorted = |obstacles: Option<&FastHashSet<i32>>| {
+            let mut obstacles: Vec<i32> = obstacles.into_iter().flatten().copied().collect();
+            obstacles.sort_unstable();
+            obstacles
+        };
 
-    // Return the position where the obstacle needs to be placed
-    Some(position_ahead)
-}
+        let mut table = Self {
+            width: map.width as usize,
+            stops: vec![None; map.width as usize * map.height as usize * 4],
+        };
 
-fn has_t_intersection(turns_in_row: &HashSet<Position>, turns_in_col: &HashSet<Position>) -> bool {
-    for row_turn in turns_in_row {
-        for col_turn in turns_in_col {
-            // Check if the intersection of row_turn and col_turn matches
-            if row_turn.x == col_turn.x && row_turn.y == col_turn.y {
-                // return Some(Position { x: row_turn.x, y: col_turn.y });
-                return true;
+        for y in 0..map.height {
+            let row = sorted(map.row_obstacles.get(&y));
+            for x in 0..map.width {
+                let before = row.partition_point(|&obstacle| obstacle < x);
+                let after = row.partition_point(|&obstacle| obstacle <= x);
+
+                let position = Position { x, y };
+                table.set(
+                    &position,
+                    &Direction::Left,
+                    before.checked_sub(1).map(|i| Position { x: row[i] + 1, y }),
+                );
+                table.set(
+                    &position,
+                    &Direction::Right,
+                    row.get(after)
+                        .map(|&obstacle| Position { x: obstacle - 1, y }),
+                );
             }
         }
+
+        for x in 0..map.width {
+            let col = sorted(map.col_obstacles.get(&x));
+            for y in 0..map.height {
+                let before = col.partition_point(|&obstacle| obstacle < y);
+                let after = col.partition_point(|&obstacle| obstacle <= y);
+
+                let position = Position { x, y };
+                table.set(
+                    &position,
+                    &Direction::Up,
+                    before.checked_sub(1).map(|i| Position { x, y: col[i] + 1 }),
+                );
+                table.set(
+                    &position,
+                    &Direction::Down,
+                    col.get(after)
+                        .map(|&obstacle| Position { x, y: obstacle - 1 }),
+                );
+            }
+        }
+
+        table
+    }
+
+    fn index(&self, position: &Position, direction: &Direction) -> usize {
+        (position.y as usize * self.width + position.x as usize) * 4 + direction.index()
+    }
+
+    fn set(&mut self, position: &Position, direction: &Direction, stop: Option<Position>) {
+        let index = self.index(position, direction);
+        self.stops[index] = stop;
+    }
+
+    fn stop(&self, position: &Position, direction: &Direction) -> Option<&Position> {
+        self.stops[self.index(position, direction)].as_ref()
     }
-    // None
-    false
 }
 
 fn read_input(path: &str) -> Result<(Map, Guard)> {
-    let mut lines = read_lines(path)?;
+    let lines = read_lines(path)?;
+    parse_input(lines.map_while(Result::ok))
+}
 
-    let mut width = 0;
-    let mut obstacles: HashSet<Obstacle> = HashSet::default();
-    let mut guard_direction: Direction = Direction::Up;
-    let mut guard_position: Position = Position { x: 0, y: 0 };
+/// Reads the non-empty lines as rows of a rectangular map with exactly one guard.
+pub fn parse_input(lines: impl Iterator<Item = String>) -> Result<(Map, Guard)> {
+    let mut width = None;
+    let mut obstacles: Vec<Position> = Vec::default();
+    let mut guard: Option<Guard> = None;
 
-    let mut x = 0;
     let mut y = 0;
-    lines.map(|line| line.unwrap()).for_each(|line| {
+    for line in lines.filter(|line| !line.is_empty()) {
         let chars: Vec<char> = line.chars().collect();
-        width = chars.len();
+        let expected = *width.get_or_insert(chars.len());
+        if chars.len() != expected {
+            return Err(eyre!(
+                "Row {} has {} cells, expected {}",
+                y + 1,
+                chars.len(),
+                expected
+            ));
+        }
 
-        chars.iter().for_each(|char| {
-            match *char {
+        for (x, char) in chars.into_iter().enumerate() {
+            let position = Position {
+                x: x.try_into()?,
+                y,
+            };
+            let direction = match char {
                 '#' => {
-                    obstacles.insert(Obstacle::new(x, y));
-                }
-                '^' => {
-                    guard_position = Position { x, y };
-                    guard_direction = Direction::Up;
-                }
-                '>' => {
-                    guard_position = Position { x, y };
-                    guard_direction = Direction::Left;
-                }
-                'v' => {
-                    guard_position = Position { x, y };
-                    guard_direction = Direction::Down;
-                }
-                '<' => {
-                    guard_position = Position { x, y };
-                    guard_direction = Direction::Right;
-                }
-                '.' => (),
-                _ => {
-                    panic!("Unknown char");
+                    obstacles.push(position);
+                    continue;
                 }
+                '.' => continue,
+                '^' => Direction::Up,
+                '>' => Direction::Right,
+                'v' => Direction::Down,
+                '<' => Direction::Left,
+                _ => return Err(eyre!("Unknown {:?} at {}", char, position)),
+            };
+            if let Some(first) = &guard {
+                return Err(eyre!(
+                    "Second guard at {}, the first is at {}",
+                    position,
+                    first.position
+                ));
             }
+            guard = Some(Guard {
+                position,
+                direction,
+            });
+        }
 
-            x += 1;
-        });
+        y = y
+            .checked_add(1)
+            .ok_or_else(|| eyre!("The map has too many rows"))?;
+    }
 
-        x = 0;
-        y += 1;
-    });
+    let width: i32 = width.unwrap_or(0).try_into()?;
+    debug!("Map has height: {} and width: {}", y, width);
+    let guard = guard.ok_or_else(|| eyre!("The map has no guard"))?;
 
-    println!("Map has height: {} and width: {}", y, width);
+    let mut obstacle_set = PointSet::new(width, y);
+    for obstacle in obstacles {
+        obstacle_set.insert(obstacle.x, obstacle.y);
+    }
 
-    Ok((
-        Map::new(
-            y,
-            width.try_into().unwrap(),
-            obstacles,
-            guard_position.clone(),
-        ),
-        Guard {
-            position: guard_position,
-            direction: guard_direction,
-        },
-    ))
+    Ok((Map::new(y, width, obstacle_set), guard))
 }
 
 #[derive(Debug)]
-struct Map {
+pub struct Map {
     height: i32,
     width: i32,
-    obstacles: HashSet<Obstacle>,
-    visited: HashSet<Position>,
-    visited_directional: HashMap<Position, Direction>,
-    start_position: Position,
-    turns: HashSet<Position>,
-    x_turns: HashMap<i32, HashSet<Position>>,
-    y_turns: HashMap<i32, HashSet<Position>>,
-    row_obstacles: HashMap<i32, HashSet<i32>>,
-    col_obstacles: HashMap<i32, HashSet<i32>>,
+    obstacles: PointSet,
+    visited: PointSet,
+    row_obstacles: FastHashMap<i32, FastHashSet<i32>>,
+    col_obstacles: FastHashMap<i32, FastHashSet<i32>>,
 }
 
 impl Map {
-    fn add_turn(&mut self, position: Position) {
-        self.turns.insert(position.clone());
-
-        self.x_turns
-            .entry(position.x)
-            .or_insert_with(HashSet::new)
-            .insert(position.clone());
-
-        self.y_turns
-            .entry(position.y)
-            .or_insert_with(HashSet::new)
-            .insert(position);
-    }
-    fn new(
-        height: i32,
-        width: i32,
-        obstacles: HashSet<Obstacle>,
-        start_position: Position,
-    ) -> Self {
-        let mut row_obstacles = HashMap::new();
-        let mut col_obstacles = HashMap::new();
-
-        for obstacle in &obstacles {
-            row_obstacles
-                .entry(obstacle.position.y)
-                .or_insert_with(HashSet::new)
-                .insert(obstacle.position.x);
-            col_obstacles
-                .entry(obstacle.position.x)
-                .or_insert_with(HashSet::new)
-                .insert(obstacle.position.y);
+    fn new(height: i32, width: i32, obstacles: PointSet) -> Self {
+        let mut row_obstacles: FastHashMap<i32, FastHashSet<i32>> = FastHashMap::default();
+        let mut col_obstacles: FastHashMap<i32, FastHashSet<i32>> = FastHashMap::default();
+
+        for (x, y) in obstacles.iter() {
+            row_obstacles.entry(y).or_default().insert(x);
+            col_obstacles.entry(x).or_default().insert(y);
         }
 
         Map {
             height,
             width,
             obstacles,
-            visited: HashSet::new(),
-            visited_directional: HashMap::new(),
-            start_position,
-            turns: HashSet::new(),
-            x_turns: HashMap::new(),
-            y_turns: HashMap::new(),
+            visited: PointSet::new(width, height),
             row_obstacles,
             col_obstacles,
         }
     }
+
+    /// Cells the guard walked on so far.
+    pub fn visited(&self) -> &PointSet {
+        &self.visited
+    }
+
     fn is_out_of_bounds(&self, position: &Position) -> bool {
         position.x < 0 || position.x >= self.width || position.y < 0 || position.y >= self.height
     }
     fn is_blocked(&self, position: &Position) -> bool {
-        self.obstacles.contains(&Obstacle {
-            position: position.clone(),
-        })
+        self.obstacles.contains(position.x, position.y)
     }
-    fn turn_in_same_row_and_col(&self, position: &Position) -> bool {
-        // Check for turns in the same row
-        let turn_in_row = self.turns.iter().any(|turn| turn.y == position.y);
+}
 
-        // Check for turns in the same col
-        let turn_in_col = self.turns.iter().any(|turn| turn.x == position.x);
+#[derive(Debug, Eq, Hash, PartialEq, Clone)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
 
-        turn_in_row && turn_in_col
+impl Direction {
+    fn letter(&self) -> char {
+        match self {
+            Direction::Up => 'U',
+            Direction::Right => 'R',
+            Direction::Down => 'D',
+            Direction::Left => 'L',
+        }
     }
 
-    fn turns_in_same_row(&self, position: &Position) -> HashSet<Position> {
-        self.turns
-            .iter()
-            .filter(|turn| turn.y == position.y)
-            .cloned()
-            .collect()
+    fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'U' => Some(Direction::Up),
+            'R' => Some(Direction::Right),
+            'D' => Some(Direction::Down),
+            'L' => Some(Direction::Left),
+            _ => None,
+        }
     }
 
-    fn turns_in_same_col(&self, position: &Position) -> HashSet<Position> {
-        self.turns
-            .iter()
-            .filter(|turn| turn.x == position.x)
-            .cloned()
-            .collect()
+    fn index(&self) -> usize {
+        match self {
+            Direction::Up => 0,
+            Direction::Right => 1,
+            Direction::Down => 2,
+            Direction::Left => 3,
+        }
     }
 
-    fn print_state(&self, guard: &Guard) {
-        let term = Term::stdout();
-        term.clear_screen().unwrap(); // Clear the screen before printing
-
-        println!("");
-        for y in (0..self.height).rev() {
-            print!(" ");
-            // Print from top to bottom
-            for x in 0..self.width {
-                let position = Position { x, y };
-
-                // Check if this position is where the guard is
-                if guard.position == position {
-                    let guard_char = match guard.direction {
-                        Direction::Up => '^',
-                        Direction::Right => '>',
-                        Direction::Down => 'v',
-                        Direction::Left => '<',
-                    };
-                    print!("{}", style(guard_char).cyan().bold());
-                }
-                // Check if the position is an obstacle
-                else if self.obstacles.contains(&Obstacle {
-                    position: position.clone(),
-                }) {
-                    print!("{}", style("#").red());
-                }
-                // Check if the position was visited
-                else if self.visited.contains(&position) {
-                    print!("{}", style("X").dim());
-                }
-                // Default representation for unvisited and unobstructed positions
-                else {
-                    print!("{}", style(".").dim());
-                }
-            }
-            println!(); // Move to the next line
+    /// Step in map coordinates, `y` grows downwards.
+    fn offset(&self) -> (i32, i32) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Right => (1, 0),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
         }
     }
-}
-
-#[derive(Debug, Eq, Hash, PartialEq, Clone)]
-enum Direction {
-    Up,
-    Down,
-    Left,
-    Right,
-}
 
-impl Direction {
     fn turn(self) -> Self {
         match self {
             Direction::Up => Direction::Right,
@@ -394,63 +583,427 @@ impl Direction {
     }
 }
 
-#[derive(Debug)]
-struct Guard {
+#[derive(Debug, Clone)]
+pub struct Guard {
     position: Position,
-    // up - 1, left - 2, down - 3, right - 4
     direction: Direction,
 }
 
 impl Guard {
+    /// Steps until the guard would walk onto `position`, if it lies straight ahead.
+    fn distance_ahead(&self, position: &Position) -> Option<i32> {
+        let (dx, dy) = self.direction.offset();
+        let (x, y) = (position.x - self.position.x, position.y - self.position.y);
+
+        let in_line = (dx == 0 && x == 0) || (dy == 0 && y == 0);
+        let distance = x * dx + y * dy;
+        (in_line && distance > 0).then_some(distance)
+    }
+
     fn look_ahead(&self) -> Position {
-        let (dx, dy) = match self.direction {
-            Direction::Up => (0, 1),
-            Direction::Right => (-1, 0),
-            Direction::Down => (0, -1),
-            Direction::Left => (1, 0),
-        };
+        self.position.moved(&self.direction, 1)
+    }
+
+    fn move_ahead(&mut self) {
+        self.position = self.look_ahead();
+    }
+}
 
+#[derive(PartialEq, Clone, Debug, Hash, Eq)]
+pub struct Position {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl Position {
+    fn distance(&self, other: &Position) -> i32 {
+        (self.x - other.x).abs() + (self.y - other.y).abs()
+    }
+
+    fn moved(&self, direction: &Direction, steps: i32) -> Position {
+        let (dx, dy) = direction.offset();
         Position {
-            x: self.position.x - dx,
-            y: self.position.y - dy,
+            x: self.x + dx * steps,
+            y: self.y + dy * steps,
         }
     }
-    fn move_ahead(&mut self) {
-        let (dx, dy) = match self.direction {
-            Direction::Up => (0, 1),
-            Direction::Right => (-1, 0),
-            Direction::Down => (0, -1),
-            Direction::Left => (1, 0),
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {})", self.x, self.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzing::run_bounded;
+    use crate::generate::{Generator, GuardMap};
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+    use std::collections::HashSet;
+
+    const EXAMPLE: &str = "\
+....#.....
+.........#
+..........
+..#.......
+.......#..
+..........
+.#..^.....
+........#.
+#.........
+......#...";
+
+    fn example() -> (Map, Guard) {
+        parse_input(EXAMPLE.lines().map(String::from)).unwrap()
+    }
+
+    #[test]
+    fn example_part_1() {
+        let (mut map, guard) = example();
+        move_guard(&mut map, guard, |_, _| Ok(())).unwrap();
+        assert_eq!(map.visited.len(), 41);
+    }
+
+    #[test]
+    fn example_part_2() {
+        let (mut map, guard) = example();
+        move_guard(&mut map, guard.clone(), |_, _| Ok(())).unwrap();
+        assert_eq!(count_loop_obstructions(&map, &guard), 6);
+    }
+
+    #[test]
+    fn obstruction_loops() {
+        let (map, guard) = example();
+        let jumps = JumpTable::new(&map);
+        assert!(is_loop_with(
+            &jumps,
+            guard.clone(),
+            &Position { x: 3, y: 6 }
+        ));
+        assert!(is_loop_with(
+            &jumps,
+            guard.clone(),
+            &Position { x: 7, y: 9 }
+        ));
+        assert!(!is_loop_with(
+            &jumps,
+            guard.clone(),
+            &Position { x: 1, y: 1 }
+        ));
+        assert!(!is_loop_with(&jumps, guard, &Position { x: 4, y: 5 }));
+    }
+
+    #[test]
+    fn trajectory_queries() {
+        let (mut map, guard) = example();
+        let trajectory = move_guard(&mut map, guard, |_, _| Ok(())).unwrap();
+
+        assert_eq!(trajectory.steps()[0].kind, StepKind::Start);
+        assert_eq!(trajectory.first_visit(&Position { x: 4, y: 6 }), Some(0));
+        assert_eq!(trajectory.first_visit(&Position { x: 4, y: 1 }), Some(5));
+        assert_eq!(trajectory.first_visit(&Position { x: 0, y: 0 }), None);
+        assert_eq!(
+            trajectory.directions_at(&Position { x: 4, y: 6 }),
+            vec![Direction::Up, Direction::Left]
+        );
+
+        let segment = trajectory.segment(0, 1).unwrap();
+        assert_eq!(segment.first().unwrap().position, Position { x: 4, y: 1 });
+        assert_eq!(segment.last().unwrap().position, Position { x: 8, y: 1 });
+        assert_eq!(segment.len(), 6);
+        assert!(trajectory.segment(1, 0).is_none());
+    }
+
+    #[test]
+    fn trajectory_from_map() {
+        let trajectory = Trajectory::from_map(EXAMPLE).unwrap();
+        assert_eq!(trajectory.turns().count(), 10);
+        assert_eq!(
+            trajectory.steps().last().unwrap().position,
+            Position { x: 7, y: 9 }
+        );
+
+        let err = Trajectory::from_map(".#..\n...#\n#^..\n..#.").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "The guard walks in a loop, it turned at (1, 1) before"
+        );
+    }
+
+    #[test]
+    fn trajectory_text_round_trip() {
+        let (mut map, guard) = example();
+        let trajectory = move_guard(&mut map, guard, |_, _| Ok(())).unwrap();
+
+        let text = trajectory.to_string();
+        assert!(text.starts_with("4,6 U5 R4 D5 L6"));
+        assert_eq!(text.parse::<Trajectory>().unwrap(), trajectory);
+
+        assert!("4,6 U5 L2".parse::<Trajectory>().is_err());
+        assert!("U5".parse::<Trajectory>().is_err());
+    }
+
+    #[test]
+    fn bad_maps() {
+        let parse = |map: &str| {
+            parse_input(map.lines().map(String::from))
+                .unwrap_err()
+                .to_string()
         };
+        assert_eq!(parse("..#\n.^\n"), "Row 2 has 2 cells, expected 3");
+        assert_eq!(parse(".^.\n.x.\n"), "Unknown 'x' at (1, 1)");
+        assert_eq!(
+            parse("^..\n..<\n"),
+            "Second guard at (2, 1), the first is at (0, 0)"
+        );
+        assert_eq!(parse("..#\n...\n"), "The map has no guard");
+        assert_eq!(parse(""), "The map has no guard");
+    }
 
-        self.position = Position {
-            x: self.position.x - dx,
-            y: self.position.y - dy,
+    #[test]
+    fn fuzz_input_bounded() {
+        let map = |loop_free| GuardMap {
+            width: 16,
+            height: 16,
+            density: 0.15,
+            loop_free,
+        };
+        run_bounded(
+            &[
+                EXAMPLE.as_bytes().to_vec(),
+                b"#.\n^#\n".to_vec(),
+                b".#.\n.^#\n...\n".to_vec(),
+                map(true).generate(1).into_bytes(),
+                map(false).generate(2).into_bytes(),
+            ],
+            fuzz_input,
+        );
+    }
+
+    #[test]
+    fn jump_table_stops() {
+        let (map, _) = example();
+        let jumps = JumpTable::new(&map);
+        let start = Position { x: 4, y: 6 };
+
+        assert_eq!(
+            jumps.stop(&start, &Direction::Up),
+            Some(&Position { x: 4, y: 1 })
+        );
+        assert_eq!(
+            jumps.stop(&start, &Direction::Left),
+            Some(&Position { x: 2, y: 6 })
+        );
+        assert_eq!(jumps.stop(&start, &Direction::Right), None);
+        assert_eq!(jumps.stop(&start, &Direction::Down), None);
+    }
+
+    /// Walks the guard one cell at a time and returns the number of visited cells, or
+    /// `None` if it comes back to a cell facing the same way.
+    fn naive_walk(grid: &[Vec<bool>], (mut x, mut y): (i32, i32)) -> Option<usize> {
+        let (width, height) = (grid[0].len() as i32, grid.len() as i32);
+        let (mut dx, mut dy) = (0, -1);
+        let mut seen = HashSet::new();
+
+        while seen.insert((x, y, dx, dy)) {
+            let (ax, ay) = (x + dx, y + dy);
+            if ax < 0 || ay < 0 || ax >= width || ay >= height {
+                let cells: HashSet<(i32, i32)> = seen.iter().map(|&(x, y, _, _)| (x, y)).collect();
+                return Some(cells.len());
+            }
+            if grid[ay as usize][ax as usize] {
+                (dx, dy) = (-dy, dx);
+            } else {
+                (x, y) = (ax, ay);
+            }
         }
+        None
     }
-}
 
-#[derive(Debug, Eq, Hash, PartialEq)]
-struct Obstacle {
-    position: Position,
-}
+    /// Tries an obstruction on every free cell but the start.
+    fn naive_loop_obstructions(grid: &[Vec<bool>], start: (i32, i32)) -> usize {
+        let mut count = 0;
+        for y in 0..grid.len() {
+            for x in 0..grid[y].len() {
+                if grid[y][x] || (x as i32, y as i32) == start {
+                    continue;
+                }
+                let mut obstructed = grid.to_vec();
+                obstructed[y][x] = true;
+                if naive_walk(&obstructed, start).is_none() {
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
 
-impl Obstacle {
-    fn new(x: i32, y: i32) -> Self {
-        Self {
-            position: Position { x, y },
+    /// Maps with a few obstacles and the guard somewhere facing up.
+    fn maps() -> impl Strategy<Value = (Vec<Vec<bool>>, (i32, i32))> {
+        (1..9usize, 1..9usize).prop_flat_map(|(width, height)| {
+            (
+                vec(vec(proptest::bool::weighted(0.2), width), height),
+                (0..width as i32, 0..height as i32),
+            )
+                .prop_map(|(mut grid, (x, y))| {
+                    grid[y as usize][x as usize] = false;
+                    (grid, (x, y))
+                })
+        })
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(2000))]
+
+        #[test]
+        fn matches_naive((grid, start) in maps()) {
+            let visited = naive_walk(&grid, start);
+            // The guard has to leave the map for part 1 to end.
+            prop_assume!(visited.is_some());
+
+            let lines = grid.iter().enumerate().map(|(y, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(|(x, &obstacle)| match (x as i32, y as i32) {
+                        position if position == start => '^',
+                        _ if obstacle => '#',
+                        _ => '.',
+                    })
+                    .collect::<String>()
+            });
+            let (mut map, guard) = parse_input(lines).unwrap();
+            move_guard(&mut map, guard.clone(), |_, _| Ok(())).unwrap();
+
+            prop_assert_eq!(Some(map.visited.len()), visited);
+            prop_assert_eq!(
+                count_loop_obstructions(&map, &guard),
+                naive_loop_obstructions(&grid, start)
+            );
         }
     }
 }
 
-#[derive(PartialEq, Clone, Debug, Hash, Eq)]
-struct Position {
-    x: i32,
-    y: i32,
-}
+#[cfg(any(feature = "viz", feature = "export"))]
+mod render {
+    use super::{Direction, Guard, Map};
+    use crate::frame::{Frame, Legend};
+    use color_eyre::Result;
+    use console::Style;
+
+    const VISITED: usize = 1;
+    const OBSTACLE: usize = 2;
+    const GUARD: usize = 3;
+
+    #[cfg_attr(not(feature = "viz"), allow(dead_code))]
+    fn legend() -> Legend {
+        Legend::new()
+            .entry('.', "unvisited", Style::new().dim())
+            .entry('X', "visited", Style::new().yellow())
+            .entry('#', "obstacle", Style::new().red())
+            .entry('^', "guard", Style::new().cyan().bold())
+    }
 
-impl fmt::Display for Position {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {})", self.x, self.y)
+    #[cfg(feature = "export")]
+    fn palette() -> crate::export::Palette {
+        crate::export::Palette::new(vec![
+            [0x1e, 0x1e, 0x2e],
+            [0xf9, 0xe2, 0xaf],
+            [0xf3, 0x8b, 0xa8],
+            [0x89, 0xdc, 0xeb],
+        ])
+    }
+
+    /// Sends every step of the guard to the outputs requested on the command line.
+    pub struct Observer {
+        #[cfg(feature = "viz")]
+        visualizer: Option<crate::viz::Visualizer>,
+        #[cfg(feature = "export")]
+        exporter: Option<crate::export::Exporter>,
+        #[cfg(feature = "export")]
+        last_guard: Option<Guard>,
+    }
+
+    impl Observer {
+        pub fn from_run_options() -> Result<Self> {
+            let options = crate::run_options();
+
+            Ok(Self {
+                #[cfg(feature = "viz")]
+                visualizer: options
+                    .visualize
+                    .then(|| crate::viz::Visualizer::new("Day 6: Guard Gallivant", legend()))
+                    .transpose()?
+                    .map(|visualizer| {
+                        visualizer.with_fps(options.fps.unwrap_or(crate::viz::DEFAULT_FPS))
+                    }),
+                #[cfg(feature = "export")]
+                exporter: options
+                    .export
+                    .as_ref()
+                    .map(|export| crate::export::Exporter::new(export, palette()))
+                    .transpose()?,
+                #[cfg(feature = "export")]
+                last_guard: None,
+            })
+        }
+
+        pub fn step(&mut self, map: &Map, guard: &Guard) -> Result<()> {
+            #[cfg(feature = "viz")]
+            if let Some(visualizer) = self.visualizer.as_mut() {
+                if !visualizer.is_stopped() {
+                    visualizer.show(&map.frame(guard))?;
+                }
+            }
+
+            #[cfg(feature = "export")]
+            if let Some(exporter) = self.exporter.as_mut() {
+                exporter.trace(guard.position.x as usize, guard.position.y as usize);
+                exporter.record(|| map.frame(guard))?;
+                self.last_guard = Some(guard.clone());
+            }
+
+            Ok(())
+        }
+
+        #[cfg_attr(not(feature = "export"), allow(unused_variables))]
+        pub fn finish(self, map: &Map) -> Result<()> {
+            #[cfg(feature = "export")]
+            if let (Some(exporter), Some(guard)) = (self.exporter, self.last_guard) {
+                exporter.finish(&map.frame(&guard))?;
+            }
+
+            Ok(())
+        }
+    }
+
+    impl Map {
+        fn frame(&self, guard: &Guard) -> Frame {
+            let mut frame = Frame::new(self.width as usize, self.height as usize);
+
+            for (x, y) in self.visited.iter() {
+                frame.set(x as usize, y as usize, VISITED);
+            }
+            for (x, y) in self.obstacles.iter() {
+                frame.set(x as usize, y as usize, OBSTACLE);
+            }
+
+            let guard_char = match guard.direction {
+                Direction::Up => '^',
+                Direction::Right => '>',
+                Direction::Down => 'v',
+                Direction::Left => '<',
+            };
+            frame.set_glyph(
+                guard.position.x as usize,
+                guard.position.y as usize,
+                GUARD,
+                guard_char,
+            );
+
+            frame
+        }
     }
 }
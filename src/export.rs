@@ -0,0 +1,456 @@
+//! Exports grid simulations as image files, enabled with the `export` feature.
+//!
+//! The format follows from the file extension:
+//! - `.png` the final frame
+//! - `.gif` an animation of the recorded frames
+//! - `.svg` the final frame with the traced path drawn on top
+
+use crate::frame::Frame;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+/// Animations end after this many frames, only the final frame is added after it.
+pub const MAX_GIF_FRAMES: usize = 1000;
+
+pub type Rgb = [u8; 3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    Gif,
+    Svg,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "png" => Some(Format::Png),
+            "gif" => Some(Format::Gif),
+            "svg" => Some(Format::Svg),
+            _ => None,
+        }
+    }
+}
+
+/// Colours of the cell kinds of a [`Frame`], indexed like its [`Legend`](crate::frame::Legend).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    colors: Vec<Rgb>,
+    path: Rgb,
+}
+
+impl Palette {
+    pub fn new(colors: Vec<Rgb>) -> Self {
+        assert!(
+            !colors.is_empty() && colors.len() < 256,
+            "A palette needs between 1 and 255 colors"
+        );
+        Self {
+            colors,
+            path: [0x00, 0xaa, 0xff],
+        }
+    }
+
+    pub fn with_path_color(mut self, color: Rgb) -> Self {
+        self.path = color;
+        self
+    }
+
+    /// Replaces the first colours with the ones given as comma separated hex codes,
+    /// e.g. `"1e1e1e,ffcc00"`.
+    pub fn override_with(mut self, spec: &str) -> Result<Self, String> {
+        for (kind, code) in spec.split(',').map(str::trim).enumerate() {
+            let color = parse_hex(code).ok_or_else(|| format!("Invalid color: {}", code))?;
+            match self.colors.get_mut(kind) {
+                Some(existing) => *existing = color,
+                None => return Err(format!("Palette has no color number {}", kind + 1)),
+            }
+        }
+        Ok(self)
+    }
+
+    fn color(&self, kind: usize) -> Rgb {
+        self.colors.get(kind).copied().unwrap_or([0xff, 0x00, 0xff])
+    }
+}
+
+fn parse_hex(code: &str) -> Option<Rgb> {
+    let code = code.strip_prefix('#').unwrap_or(code);
+    if code.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(code.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// What to export, as given on the command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportOptions {
+    pub path: PathBuf,
+    /// Edge length of one cell in pixels.
+    pub cell_size: u16,
+    /// Only every n-th frame ends up in an animation.
+    pub frame_step: usize,
+    /// Comma separated hex colours replacing the ones of the day's palette.
+    pub palette: Option<String>,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::new(),
+            cell_size: 4,
+            frame_step: 50,
+            palette: None,
+        }
+    }
+}
+
+/// Receives the frames of a simulation and writes them in the requested format.
+pub struct Exporter {
+    path: PathBuf,
+    cell_size: usize,
+    frame_step: usize,
+    palette: Palette,
+    frame_count: usize,
+    gif_frames: usize,
+    last_written: bool,
+    trace: Vec<(usize, usize)>,
+    gif: Option<gif::Encoder<BufWriter<File>>>,
+}
+
+impl Exporter {
+    pub fn new(options: &ExportOptions, palette: Palette) -> io::Result<Self> {
+        let palette = match &options.palette {
+            Some(spec) => palette
+                .override_with(spec)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?,
+            None => palette,
+        };
+
+        if Format::from_path(&options.path).is_none() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Can't export to {}", options.path.display()),
+            ));
+        }
+
+        Ok(Self {
+            path: options.path.clone(),
+            cell_size: options.cell_size.max(1) as usize,
+            frame_step: options.frame_step.max(1),
+            palette,
+            frame_count: 0,
+            gif_frames: 0,
+            last_written: false,
+            trace: vec![],
+            gif: None,
+        })
+    }
+
+    fn format(&self) -> Format {
+        Format::from_path(&self.path).expect("Exporter is created with a known format")
+    }
+
+    /// Records the next frame of an animation, `render` is only called for the frames that
+    /// end up in the file. The still formats only need the final frame passed to
+    /// [`Exporter::finish`].
+    pub fn record(&mut self, render: impl FnOnce() -> Frame) -> io::Result<()> {
+        let due = self.frame_count.is_multiple_of(self.frame_step);
+        self.frame_count += 1;
+        self.last_written = false;
+        if self.format() != Format::Gif || !due {
+            return Ok(());
+        }
+
+        match self.gif_frames.cmp(&MAX_GIF_FRAMES) {
+            Ordering::Less => {
+                self.write_gif_frame(&render())?;
+                self.last_written = true;
+            }
+            Ordering::Equal => {
+                warn!(
+                    "Animation reached {} frames, leaving out the rest but the final one",
+                    MAX_GIF_FRAMES
+                );
+                // Counted once more so the warning is not repeated.
+                self.gif_frames += 1;
+            }
+            Ordering::Greater => (),
+        }
+        Ok(())
+    }
+
+    /// Adds a cell to the path drawn on top of the SVG.
+    pub fn trace(&mut self, x: usize, y: usize) {
+        if self.trace.last() != Some(&(x, y)) {
+            self.trace.push((x, y));
+        }
+    }
+
+    /// Writes the file with the final frame, which also ends an animation unless it was
+    /// the last frame recorded.
+    pub fn finish(mut self, frame: &Frame) -> io::Result<()> {
+        match self.format() {
+            Format::Png => write_png(&self.path, frame, &self.palette, self.cell_size),
+            Format::Svg => {
+                let mut file = BufWriter::new(File::create(&self.path)?);
+                let svg = render_svg(frame, &self.trace, &self.palette, self.cell_size);
+                file.write_all(svg.as_bytes())?;
+                file.flush()
+            }
+            Format::Gif => {
+                if !self.last_written {
+                    self.write_gif_frame(frame)?;
+                }
+                if let Some(encoder) = self.gif.take() {
+                    encoder.into_inner().map_err(io::Error::other)?.flush()?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn write_gif_frame(&mut self, frame: &Frame) -> io::Result<()> {
+        let width = frame.width() * self.cell_size;
+        let height = frame.height() * self.cell_size;
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Frame is too large for a gif",
+            ));
+        }
+
+        if self.gif.is_none() {
+            let flat_palette: Vec<u8> = self.palette.colors.iter().flatten().copied().collect();
+            let file = BufWriter::new(File::create(&self.path)?);
+            let mut encoder = gif::Encoder::new(file, width as u16, height as u16, &flat_palette)
+                .map_err(io::Error::other)?;
+            encoder
+                .set_repeat(gif::Repeat::Infinite)
+                .map_err(io::Error::other)?;
+            self.gif = Some(encoder);
+        }
+
+        let max_kind = self.palette.colors.len() - 1;
+        let pixels = scale(frame, self.cell_size, |kind| kind.min(max_kind) as u8);
+        let mut gif_frame =
+            gif::Frame::from_indexed_pixels(width as u16, height as u16, pixels, None);
+        gif_frame.delay = 2;
+        self.gif_frames += 1;
+
+        self.gif
+            .as_mut()
+            .expect("gif encoder was just created")
+            .write_frame(&gif_frame)
+            .map_err(io::Error::other)
+    }
+}
+
+/// Expands every cell of the frame to `cell_size` x `cell_size` pixels.
+fn scale<T: Copy>(frame: &Frame, cell_size: usize, pixel: impl Fn(usize) -> T) -> Vec<T> {
+    let mut pixels = Vec::with_capacity(frame.width() * frame.height() * cell_size * cell_size);
+    for row in frame.rows() {
+        for _ in 0..cell_size {
+            for cell in row {
+                for _ in 0..cell_size {
+                    pixels.push(pixel(cell.kind));
+                }
+            }
+        }
+    }
+    pixels
+}
+
+pub fn write_png(
+    path: &Path,
+    frame: &Frame,
+    palette: &Palette,
+    cell_size: usize,
+) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(
+        file,
+        (frame.width() * cell_size) as u32,
+        (frame.height() * cell_size) as u32,
+    );
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let pixels: Vec<u8> = scale(frame, cell_size, |kind| palette.color(kind))
+        .into_iter()
+        .flatten()
+        .collect();
+
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&pixels))
+        .map_err(io::Error::other)
+}
+
+pub fn render_svg(
+    frame: &Frame,
+    trace: &[(usize, usize)],
+    palette: &Palette,
+    cell_size: usize,
+) -> String {
+    let hex = |[r, g, b]: Rgb| format!("#{:02x}{:02x}{:02x}", r, g, b);
+    let width = frame.width() * cell_size;
+    let height = frame.height() * cell_size;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" shape-rendering=\"crispEdges\">\n",
+        width, height, width, height
+    );
+    svg.push_str(&format!(
+        "<rect width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+        width,
+        height,
+        hex(palette.color(0))
+    ));
+
+    for (y, row) in frame.rows().enumerate() {
+        for (x, cell) in row.iter().enumerate() {
+            if cell.kind == 0 {
+                continue;
+            }
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                x * cell_size,
+                y * cell_size,
+                cell_size,
+                cell_size,
+                hex(palette.color(cell.kind))
+            ));
+        }
+    }
+
+    if !trace.is_empty() {
+        let center = |value: usize| value * cell_size + cell_size / 2;
+        let points: Vec<String> = trace
+            .iter()
+            .map(|&(x, y)| format!("{},{}", center(x), center(y)))
+            .collect();
+        svg.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linejoin=\"round\"/>\n",
+            points.join(" "),
+            hex(palette.path),
+            (cell_size / 3).max(1)
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn palette_override() {
+        let palette = Palette::new(vec![[0, 0, 0], [1, 1, 1]]);
+
+        let palette = palette.override_with("#ff0000").unwrap();
+        assert_eq!(palette.color(0), [0xff, 0, 0]);
+        assert_eq!(palette.color(1), [1, 1, 1]);
+
+        assert!(palette
+            .clone()
+            .override_with("ff0000,00ff00,0000ff")
+            .is_err());
+        assert!(palette.override_with("red").is_err());
+    }
+
+    #[test]
+    fn svg_overlay() {
+        let mut frame = Frame::new(3, 2);
+        frame.set(1, 1, 1);
+        let palette = Palette::new(vec![[0, 0, 0], [0xff, 0xff, 0xff]]);
+
+        let svg = render_svg(&frame, &[(0, 0), (2, 0)], &palette, 10);
+        assert!(svg.contains("width=\"30\" height=\"20\""));
+        assert!(
+            svg.contains("<rect x=\"10\" y=\"10\" width=\"10\" height=\"10\" fill=\"#ffffff\"/>")
+        );
+        assert!(svg.contains("points=\"5,5 25,5\""));
+    }
+
+    /// A file in the temp directory, removed again when dropped.
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str) -> Self {
+            let name = format!("aoc2024-export-{}-{}", std::process::id(), name);
+            Self(std::env::temp_dir().join(name))
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn export(file: &TempFile, frame_step: usize, frames: usize) -> Frame {
+        let options = ExportOptions {
+            path: file.0.clone(),
+            cell_size: 3,
+            frame_step,
+            palette: None,
+        };
+        let palette = Palette::new(vec![[0, 0, 0], [0xff, 0xcc, 0]]);
+        let mut exporter = Exporter::new(&options, palette).unwrap();
+
+        let mut frame = Frame::new(5, 2);
+        for step in 0..frames {
+            frame.set(step % 5, step / 5 % 2, 1);
+            exporter.record(|| frame.clone()).unwrap();
+        }
+        exporter.finish(&frame).unwrap();
+        frame
+    }
+
+    #[test]
+    fn png_round_trip() {
+        let file = TempFile::new("frame.png");
+        export(&file, 1, 7);
+
+        let decoder = png::Decoder::new(File::open(&file.0).unwrap());
+        let mut reader = decoder.read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (15, 6));
+        // The seventh step set cell (1, 1), its pixels start at (3, 3).
+        let at = |x: usize, y: usize| &pixels[(y * 15 + x) * 3..][..3];
+        assert_eq!(at(3, 3), [0xff, 0xcc, 0]);
+        assert_eq!(at(6, 3), [0, 0, 0]);
+    }
+
+    #[test]
+    fn gif_round_trip() {
+        let count_frames = |frame_step, frames| {
+            let file = TempFile::new(&format!("{}-{}.gif", frame_step, frames));
+            export(&file, frame_step, frames);
+
+            let mut options = gif::DecodeOptions::new();
+            options.set_color_output(gif::ColorOutput::Indexed);
+            let mut decoder = options.read_info(File::open(&file.0).unwrap()).unwrap();
+            assert_eq!((decoder.width(), decoder.height()), (15, 6));
+            let mut count = 0;
+            while decoder.read_next_frame().unwrap().is_some() {
+                count += 1;
+            }
+            count
+        };
+
+        // Frames 0, 3 and 6 are recorded, the final one was among them.
+        assert_eq!(count_frames(3, 7), 3);
+        // Frames 0 and 3, then the final frame.
+        assert_eq!(count_frames(3, 6), 3);
+        assert_eq!(count_frames(1, MAX_GIF_FRAMES + 5), MAX_GIF_FRAMES + 1);
+    }
+}
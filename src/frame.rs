@@ -0,0 +1,136 @@
+//! Renderer independent snapshots of grid simulations.
+
+use console::Style;
+
+/// Describes how each cell kind of a [`Frame`] is drawn. Kinds are indices into the legend,
+/// in the order the entries were added.
+#[derive(Debug, Clone, Default)]
+pub struct Legend {
+    entries: Vec<LegendEntry>,
+}
+
+#[derive(Debug, Clone)]
+pub struct LegendEntry {
+    pub glyph: char,
+    pub label: String,
+    pub style: Style,
+}
+
+impl Legend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entry(mut self, glyph: char, label: &str, style: Style) -> Self {
+        self.entries.push(LegendEntry {
+            glyph,
+            label: label.to_string(),
+            style,
+        });
+        self
+    }
+
+    pub fn get(&self, kind: usize) -> Option<&LegendEntry> {
+        self.entries.get(kind)
+    }
+
+    pub fn entries(&self) -> &[LegendEntry] {
+        &self.entries
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Cell {
+    pub kind: usize,
+    /// Drawn instead of the legend glyph, e.g. to show the direction of a guard.
+    pub glyph: Option<char>,
+}
+
+/// Snapshot of a grid, row by row from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Frame {
+    /// Creates a frame filled with cells of kind `0`.
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, kind: usize) {
+        self.set_cell(x, y, Cell { kind, glyph: None });
+    }
+
+    pub fn set_glyph(&mut self, x: usize, y: usize, kind: usize, glyph: char) {
+        self.set_cell(
+            x,
+            y,
+            Cell {
+                kind,
+                glyph: Some(glyph),
+            },
+        );
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<Cell> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        Some(self.cells[y * self.width + x])
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[Cell]> {
+        self.cells.chunks(self.width.max(1))
+    }
+
+    fn set_cell(&mut self, x: usize, y: usize, cell: Cell) {
+        if x >= self.width || y >= self.height {
+            panic!("Tried to set ({}, {}) outside of the frame", x, y);
+        }
+        self.cells[y * self.width + x] = cell;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_cells() {
+        let mut frame = Frame::new(3, 2);
+        frame.set(2, 1, 1);
+        frame.set_glyph(0, 0, 2, '^');
+
+        assert_eq!(
+            frame.get(2, 1),
+            Some(Cell {
+                kind: 1,
+                glyph: None
+            })
+        );
+        assert_eq!(
+            frame.get(0, 0),
+            Some(Cell {
+                kind: 2,
+                glyph: Some('^')
+            })
+        );
+        assert_eq!(frame.get(3, 0), None);
+        assert_eq!(frame.rows().count(), 2);
+    }
+}
@@ -0,0 +1,185 @@
+//! Helpers shared by the fuzz targets in `fuzz/` and their bounded runs under `cargo test`.
+//!
+//! A target takes arbitrary bytes and panics if something is wrong. With `cargo fuzz` it
+//! runs for as long as libFuzzer likes, [`run_bounded`] feeds it a fixed number of inputs
+//! mutated from a corpus so every `cargo test` fuzzes a little. The targets are named after
+//! their day and what they parse, `cargo +nightly fuzz run day03_parser` runs one for real:
+//!
+//! ```
+//! use aoc2024::fuzzing::{deterministic, run_bounded};
+//!
+//! run_bounded(&[b"1,2,3".to_vec()], |data| {
+//!     deterministic(data, |data| String::from_utf8_lossy(data).split(',').count());
+//! });
+//! ```
+
+use crate::generate::Rng;
+use std::fmt::Debug;
+
+/// Inputs per target and `cargo test` run, `FUZZ_ITERATIONS` overrides it.
+pub const DEFAULT_ITERATIONS: usize = 500;
+/// Inputs never grow beyond this, like libFuzzer's default `-max_len`.
+pub const MAX_LEN: usize = 4096;
+
+/// Bytes that mean something to at least one of the input formats.
+const DICTIONARY: &[&[u8]] = &[
+    b"\n",
+    b"\r\n",
+    b" ",
+    b"   ",
+    b",",
+    b"|",
+    b"(",
+    b")",
+    b"#",
+    b"^",
+    b">",
+    b"v",
+    b"<",
+    b".",
+    b"-",
+    b"0",
+    b"9",
+    b"999",
+    b"4294967296",
+    b"mul(",
+    b"do()",
+    b"don't()",
+    b"XMAS",
+    b"\xff",
+];
+
+/// Parses the input twice and panics if the results differ, returns the result.
+pub fn deterministic<T: PartialEq + Debug>(data: &[u8], parse: impl Fn(&[u8]) -> T) -> T {
+    let first = parse(data);
+    let second = parse(data);
+    assert_eq!(
+        first,
+        second,
+        "Parsing {:?} twice gave different results",
+        String::from_utf8_lossy(data)
+    );
+    first
+}
+
+/// Changes inputs the way a fuzzer does, with seeded random numbers so a failing run can
+/// be repeated.
+pub struct Mutator {
+    rng: Rng,
+}
+
+impl Mutator {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Rng::new(seed),
+        }
+    }
+
+    /// Random bytes, up to `max_len` of them.
+    pub fn random(&mut self, max_len: usize) -> Vec<u8> {
+        let len = self.rng.below(max_len as u64 + 1) as usize;
+        (0..len).map(|_| self.rng.below(256) as u8).collect()
+    }
+
+    /// A copy of the input with one to four random changes.
+    pub fn mutate(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut data = input.to_vec();
+        for _ in 0..=self.rng.below(4) {
+            let at = self.rng.below(data.len() as u64 + 1) as usize;
+            match self.rng.below(5) {
+                0 if at < data.len() => data[at] ^= 1 << self.rng.below(8),
+                1 => {
+                    let word = self.rng.choose(DICTIONARY);
+                    data.splice(at..at, word.iter().copied());
+                }
+                2 => {
+                    let end = (at + self.rng.below(16) as usize).min(data.len());
+                    data.drain(at..end);
+                }
+                3 => {
+                    let end = (at + self.rng.below(64) as usize).min(data.len());
+                    let copy = data[at..end].to_vec();
+                    let to = self.rng.below(data.len() as u64 + 1) as usize;
+                    data.splice(to..to, copy);
+                }
+                _ => data.insert(at, self.rng.below(256) as u8),
+            }
+        }
+        data.truncate(MAX_LEN);
+        data
+    }
+}
+
+/// Calls the target on every corpus entry, then on random bytes and mutations of the
+/// corpus until [`DEFAULT_ITERATIONS`] or `FUZZ_ITERATIONS` inputs are done. The input
+/// that made the target panic is printed before the panic continues.
+pub fn run_bounded(corpus: &[Vec<u8>], target: impl Fn(&[u8])) {
+    let iterations = std::env::var("FUZZ_ITERATIONS")
+        .ok()
+        .and_then(|iterations| iterations.parse().ok())
+        .unwrap_or(DEFAULT_ITERATIONS);
+
+    let mut mutator = Mutator::new(iterations as u64);
+    let mut last: Vec<u8> = vec![];
+    for iteration in 0..iterations {
+        let data = match corpus.get(iteration) {
+            Some(entry) => entry.clone(),
+            None if corpus.is_empty() || iteration % 8 == 0 => mutator.random(256),
+            // Mutations pile up on the last input now and then, to get further from the corpus.
+            None if iteration % 3 == 0 => mutator.mutate(&last),
+            None => {
+                let entry = mutator.rng.choose(corpus);
+                mutator.mutate(entry)
+            }
+        };
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| target(&data)));
+        if let Err(panic) = result {
+            eprintln!(
+                "Fuzz target panicked on input {:?}",
+                String::from_utf8_lossy(&data)
+            );
+            std::panic::resume_unwind(panic);
+        }
+        last = data;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mutations_are_seeded() {
+        let input = b"1   2\n3   4\n";
+        let mutate = |seed| {
+            let mut mutator = Mutator::new(seed);
+            (0..20).map(|_| mutator.mutate(input)).collect::<Vec<_>>()
+        };
+        assert_eq!(mutate(1), mutate(1));
+        assert_ne!(mutate(1), mutate(2));
+        assert!(mutate(3).iter().any(|data| data != input));
+    }
+
+    #[test]
+    fn bounded_runs_see_the_corpus() {
+        let seen = std::cell::RefCell::new(vec![]);
+        run_bounded(&[b"corpus".to_vec()], |data| {
+            seen.borrow_mut().push(data.to_vec())
+        });
+
+        let seen = seen.into_inner();
+        assert_eq!(seen[0], b"corpus");
+        assert!(seen.iter().all(|data| data.len() <= MAX_LEN));
+    }
+
+    #[test]
+    #[should_panic(expected = "twice gave different results")]
+    fn nondeterminism_is_caught() {
+        let calls = std::cell::Cell::new(0);
+        deterministic(b"", |_| {
+            calls.set(calls.get() + 1);
+            calls.get()
+        });
+    }
+}
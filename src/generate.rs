@@ -0,0 +1,626 @@
+//! Seeded generators for the input formats of the implemented days.
+//!
+//! Every generator writes a whole puzzle input, so large inputs never need to be held in
+//! memory. The same seed always gives the same input:
+//!
+//! ```
+//! use aoc2024::generate::{Generator, IdLists, Rng};
+//!
+//! let lists = IdLists { lines: 3, ..Default::default() };
+//! let mut first = vec![];
+//! let mut second = vec![];
+//! lists.write(&mut Rng::new(7), &mut first).unwrap();
+//! lists.write(&mut Rng::new(7), &mut second).unwrap();
+//! assert_eq!(first, second);
+//! assert_eq!(String::from_utf8(first).unwrap().lines().count(), 3);
+//! ```
+
+use std::io::{self, Read, Write};
+
+/// Small and fast pseudo random numbers, not suitable for anything but test input.
+#[derive(Debug, Clone)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Any seed works, it is mixed with splitmix64 so similar seeds give unrelated numbers.
+    pub fn new(seed: u64) -> Self {
+        let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        Self { state: z.max(1) }
+    }
+
+    /// xorshift64.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+
+    /// A number in `0..bound`, `bound` must not be zero.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        self.next_u64() % bound
+    }
+
+    /// A number in `low..=high`.
+    pub fn between(&mut self, low: i64, high: i64) -> i64 {
+        low + self.below((high - low) as u64 + 1) as i64
+    }
+
+    /// `true` with the given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        ((self.next_u64() >> 11) as f64 / (1u64 << 53) as f64) < probability
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+}
+
+/// Writes one kind of puzzle input.
+pub trait Generator {
+    fn write(&self, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()>;
+
+    fn generate(&self, seed: u64) -> String {
+        let mut out = vec![];
+        self.write(&mut Rng::new(seed), &mut out)
+            .expect("writing to a Vec never fails");
+        String::from_utf8(out).expect("generators write UTF-8")
+    }
+}
+
+/// Day 1: two columns of ids.
+#[derive(Debug, Clone)]
+pub struct IdLists {
+    pub lines: usize,
+    /// Ids are drawn from `10_000..10_000 + distinct_ids`, fewer distinct ids mean more
+    /// ids are shared between the lists.
+    pub distinct_ids: u64,
+}
+
+impl Default for IdLists {
+    fn default() -> Self {
+        Self {
+            lines: 1000,
+            distinct_ids: 90_000,
+        }
+    }
+}
+
+impl Generator for IdLists {
+    fn write(&self, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+        let distinct = self.distinct_ids.max(1);
+        for _ in 0..self.lines {
+            let first = 10_000 + rng.below(distinct);
+            let second = 10_000 + rng.below(distinct);
+            writeln!(out, "{}   {}", first, second)?;
+        }
+        Ok(())
+    }
+}
+
+/// Day 2: reports of levels. Most steps are safe, some are flat, too large or go the
+/// other way, so all kinds of reports show up.
+#[derive(Debug, Clone)]
+pub struct Reports {
+    pub reports: usize,
+    pub min_levels: usize,
+    pub max_levels: usize,
+    /// Chance of every step to be a bad one.
+    pub bad_steps: f64,
+}
+
+impl Default for Reports {
+    fn default() -> Self {
+        Self {
+            reports: 1000,
+            min_levels: 5,
+            max_levels: 8,
+            bad_steps: 0.1,
+        }
+    }
+}
+
+impl Generator for Reports {
+    fn write(&self, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+        let max_levels = self.max_levels.max(self.min_levels);
+        for _ in 0..self.reports {
+            let levels = rng.between(self.min_levels as i64, max_levels as i64);
+            let sign = if rng.chance(0.5) { 1 } else { -1 };
+
+            let mut level = rng.between(1, 99);
+            let mut line = vec![level.to_string()];
+            for _ in 1..levels {
+                let step = if rng.chance(self.bad_steps) {
+                    *rng.choose(&[0, 4, 5, 7, -1, -2])
+                } else {
+                    rng.between(1, 3)
+                };
+                level += sign * step;
+                line.push(level.to_string());
+            }
+            writeln!(out, "{}", line.join(" "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Day 3: corrupted memory with valid and broken instructions between junk.
+#[derive(Debug, Clone)]
+pub struct CorruptedMemory {
+    pub bytes: u64,
+}
+
+impl Default for CorruptedMemory {
+    fn default() -> Self {
+        Self { bytes: 20_000 }
+    }
+}
+
+impl CorruptedMemory {
+    /// Reads the same memory [`Generator::write`] writes, generating it while it is read, for
+    /// consumers that take a reader.
+    pub fn reader(&self, rng: Rng) -> CorruptedMemoryReader {
+        CorruptedMemoryReader {
+            rng,
+            remaining: self.bytes,
+            pending: vec![],
+            start: 0,
+        }
+    }
+
+    /// Valid and broken instructions and the junk between them.
+    fn piece(rng: &mut Rng) -> String {
+        let a = rng.below(1000);
+        let b = rng.below(1000);
+        match rng.below(10) {
+            0..=2 => format!("mul({},{})", a, b),
+            3 => format!("mul({},{}]", a, b),
+            4 => format!("mul[{},{})", a, b),
+            5 => format!("mul({}, {})", a, b),
+            6 => "do()".to_string(),
+            7 => "don't()".to_string(),
+            _ => rng
+                .choose(&[
+                    "x", "%&", "!@^", "then(", "_", "+", "what()", "from()", "\n",
+                ])
+                .to_string(),
+        }
+    }
+}
+
+impl Generator for CorruptedMemory {
+    fn write(&self, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+        let mut written = 0;
+        while written < self.bytes {
+            let piece = Self::piece(rng);
+            let len = (piece.len() as u64).min(self.bytes - written);
+            out.write_all(&piece.as_bytes()[..len as usize])?;
+            written += len;
+        }
+        Ok(())
+    }
+}
+
+/// Corrupted memory generated while it is read, see [`CorruptedMemory::reader`].
+#[derive(Debug, Clone)]
+pub struct CorruptedMemoryReader {
+    rng: Rng,
+    remaining: u64,
+    pending: Vec<u8>,
+    start: usize,
+}
+
+impl Read for CorruptedMemoryReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut read = 0;
+        while read < buf.len() && self.remaining > 0 {
+            if self.start == self.pending.len() {
+                self.pending = CorruptedMemory::piece(&mut self.rng).into_bytes();
+                self.start = 0;
+            }
+            let len = (self.pending.len() - self.start)
+                .min(buf.len() - read)
+                .min(self.remaining as usize);
+            buf[read..read + len].copy_from_slice(&self.pending[self.start..self.start + len]);
+            self.start += len;
+            read += len;
+            self.remaining -= len as u64;
+        }
+        Ok(read)
+    }
+}
+
+/// Day 4: a grid of letters.
+#[derive(Debug, Clone)]
+pub struct LetterGrid {
+    pub width: usize,
+    pub height: usize,
+    pub letters: String,
+}
+
+impl Default for LetterGrid {
+    fn default() -> Self {
+        Self {
+            width: 140,
+            height: 140,
+            letters: "XMAS".to_string(),
+        }
+    }
+}
+
+impl Generator for LetterGrid {
+    fn write(&self, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+        let letters: Vec<char> = self.letters.chars().collect();
+        if letters.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A letter grid needs at least one letter",
+            ));
+        }
+
+        for _ in 0..self.height {
+            let row: String = (0..self.width).map(|_| *rng.choose(&letters)).collect();
+            writeln!(out, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+/// Day 5: page ordering rules followed by updates.
+#[derive(Debug, Clone)]
+pub struct PageRules {
+    pub pages: usize,
+    /// Chance of every pair of pages to have a rule.
+    pub density: f64,
+    pub updates: usize,
+    pub min_update: usize,
+    pub max_update: usize,
+    /// All rules follow one hidden order, so no rules form a cycle. Otherwise every rule
+    /// goes either way and cycles are all but certain.
+    pub acyclic: bool,
+}
+
+impl Default for PageRules {
+    fn default() -> Self {
+        Self {
+            pages: 49,
+            density: 1.0,
+            updates: 200,
+            min_update: 5,
+            max_update: 23,
+            acyclic: false,
+        }
+    }
+}
+
+impl Generator for PageRules {
+    fn write(&self, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+        if self.pages == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Page rules need at least one page",
+            ));
+        }
+
+        // Two digit page numbers like the real input, as long as there are enough.
+        let first = if self.pages <= 90 { 10 } else { 1 };
+        let mut order: Vec<u64> = (first..first + self.pages as u64).collect();
+        rng.shuffle(&mut order);
+
+        let mut rules = vec![];
+        for (i, &before) in order.iter().enumerate() {
+            for &after in &order[i + 1..] {
+                if !rng.chance(self.density) {
+                    continue;
+                }
+                if self.acyclic || rng.chance(0.5) {
+                    rules.push((before, after));
+                } else {
+                    rules.push((after, before));
+                }
+            }
+        }
+        rng.shuffle(&mut rules);
+        for (before, after) in rules {
+            writeln!(out, "{}|{}", before, after)?;
+        }
+        writeln!(out)?;
+
+        let max_update = self.max_update.clamp(1, self.pages.max(1));
+        let min_update = self.min_update.clamp(1, max_update);
+        for _ in 0..self.updates {
+            // Odd lengths, so every update has a middle page.
+            let mut len = rng.between(min_update as i64, max_update as i64) as usize;
+            if len.is_multiple_of(2) {
+                len = if len < max_update { len + 1 } else { len - 1 };
+            }
+            len = len.min(order.len());
+
+            let mut pages = order.clone();
+            rng.shuffle(&mut pages);
+            let update: Vec<String> = pages[..len].iter().map(u64::to_string).collect();
+            writeln!(out, "{}", update.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Day 6: a map with obstacles and a guard facing up.
+#[derive(Debug, Clone)]
+pub struct GuardMap {
+    pub width: usize,
+    pub height: usize,
+    /// Chance of every cell to be an obstacle.
+    pub density: f64,
+    /// Remove obstacles the guard loops between until it walks off the map.
+    pub loop_free: bool,
+}
+
+impl Default for GuardMap {
+    fn default() -> Self {
+        Self {
+            width: 130,
+            height: 130,
+            density: 0.05,
+            loop_free: false,
+        }
+    }
+}
+
+impl GuardMap {
+    /// The obstacle the guard last turned at once it is back in a state it was in
+    /// before, `None` if it leaves the map. A loop can't go without turns, so that
+    /// obstacle is part of it.
+    fn loop_obstacle(&self, obstacles: &[bool], guard: (usize, usize)) -> Option<(usize, usize)> {
+        const STEPS: [(isize, isize); 4] = [(0, -1), (1, 0), (0, 1), (-1, 0)];
+        let mut seen = vec![0u8; obstacles.len()];
+        let (mut x, mut y) = guard;
+        let mut facing = 0;
+        let mut last_turn = None;
+
+        loop {
+            let index = y * self.width + x;
+            if seen[index] & (1 << facing) != 0 {
+                return last_turn;
+            }
+            seen[index] |= 1 << facing;
+
+            let (dx, dy) = STEPS[facing];
+            let (ax, ay) = (x as isize + dx, y as isize + dy);
+            if ax < 0 || ay < 0 || ax >= self.width as isize || ay >= self.height as isize {
+                return None;
+            }
+
+            let ahead = (ax as usize, ay as usize);
+            if obstacles[ahead.1 * self.width + ahead.0] {
+                last_turn = Some(ahead);
+                facing = (facing + 1) % 4;
+            } else {
+                (x, y) = ahead;
+            }
+        }
+    }
+}
+
+impl Generator for GuardMap {
+    fn write(&self, rng: &mut Rng, out: &mut dyn Write) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "A guard map needs at least one cell",
+            ));
+        }
+
+        let cells = self.width * self.height;
+        let mut obstacles: Vec<bool> = (0..cells).map(|_| rng.chance(self.density)).collect();
+        let start = rng.below(cells as u64) as usize;
+        obstacles[start] = false;
+        let guard = (start % self.width, start / self.width);
+
+        if self.loop_free {
+            // Every removal takes away an obstacle, so this ends at the latest with none.
+            while let Some((x, y)) = self.loop_obstacle(&obstacles, guard) {
+                obstacles[y * self.width + x] = false;
+            }
+        }
+
+        for y in 0..self.height {
+            let row: String = (0..self.width)
+                .map(|x| match (x, y) {
+                    position if position == guard => '^',
+                    _ if obstacles[y * self.width + x] => '#',
+                    _ => '.',
+                })
+                .collect();
+            writeln!(out, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generators() -> Vec<Box<dyn Generator>> {
+        vec![
+            Box::new(IdLists::default()),
+            Box::new(Reports::default()),
+            Box::new(CorruptedMemory::default()),
+            Box::new(LetterGrid::default()),
+            Box::new(PageRules::default()),
+            Box::new(GuardMap::default()),
+        ]
+    }
+
+    #[test]
+    fn seeded() {
+        for generator in generators() {
+            assert_eq!(generator.generate(1), generator.generate(1));
+            assert_ne!(generator.generate(1), generator.generate(2));
+        }
+    }
+
+    #[test]
+    fn corrupted_memory_reader() {
+        let memory = CorruptedMemory { bytes: 5000 };
+        let mut read = String::new();
+        memory
+            .reader(Rng::new(4))
+            .read_to_string(&mut read)
+            .unwrap();
+        assert_eq!(read, memory.generate(4));
+
+        let mut reader = memory.reader(Rng::new(4));
+        let mut chunked = vec![];
+        let mut buf = [0; 7];
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                len => chunked.extend_from_slice(&buf[..len]),
+            }
+        }
+        assert_eq!(chunked, read.into_bytes());
+    }
+
+    #[test]
+    fn sizes() {
+        let lists = IdLists {
+            lines: 17,
+            distinct_ids: 5,
+        }
+        .generate(3);
+        assert_eq!(lists.lines().count(), 17);
+        assert!(lists
+            .split_whitespace()
+            .all(|id| (10_000..10_005).contains(&id.parse().unwrap())));
+
+        let reports = Reports {
+            reports: 9,
+            min_levels: 2,
+            max_levels: 4,
+            ..Default::default()
+        }
+        .generate(3);
+        assert_eq!(reports.lines().count(), 9);
+        assert!(reports
+            .lines()
+            .all(|line| (2..=4).contains(&line.split(' ').count())));
+
+        assert_eq!(CorruptedMemory { bytes: 1234 }.generate(3).len(), 1234);
+
+        let grid = LetterGrid {
+            width: 7,
+            height: 3,
+            letters: "AB".to_string(),
+        }
+        .generate(3);
+        assert_eq!(grid.lines().collect::<Vec<_>>().len(), 3);
+        assert!(grid.lines().all(|row| row.len() == 7));
+        assert!(grid.chars().all(|char| "AB\n".contains(char)));
+    }
+
+    #[test]
+    fn acyclic_rules() {
+        let generator = PageRules {
+            pages: 12,
+            density: 0.7,
+            updates: 30,
+            min_update: 3,
+            max_update: 8,
+            acyclic: true,
+        };
+        let input = generator.generate(5);
+        let (rules, updates) = input.split_once("\n\n").unwrap();
+
+        let rules: Vec<(u32, u32)> = rules
+            .lines()
+            .map(|rule| {
+                let (before, after) = rule.split_once('|').unwrap();
+                (before.parse().unwrap(), after.parse().unwrap())
+            })
+            .collect();
+        // Repeatedly dropping pages that no rule puts after another leaves nothing.
+        let mut remaining = rules.clone();
+        while !remaining.is_empty() {
+            let sources: Vec<u32> = remaining
+                .iter()
+                .map(|&(before, _)| before)
+                .filter(|page| remaining.iter().all(|&(_, after)| after != *page))
+                .collect();
+            assert!(!sources.is_empty(), "cycle in {:?}", remaining);
+            remaining.retain(|(before, _)| !sources.contains(before));
+        }
+
+        assert_eq!(updates.lines().count(), 30);
+        for update in updates.lines() {
+            let pages = update.split(',').count();
+            assert!(pages % 2 == 1 && (3..=8).contains(&pages));
+        }
+    }
+
+    #[test]
+    fn loop_free_maps() {
+        for seed in 0..20 {
+            let generator = GuardMap {
+                width: 20,
+                height: 15,
+                density: 0.3,
+                loop_free: true,
+            };
+            let map = generator.generate(seed);
+            assert_eq!(map.matches('^').count(), 1);
+
+            let obstacles: Vec<bool> = map
+                .lines()
+                .flat_map(|row| row.chars())
+                .map(|cell| cell == '#')
+                .collect();
+            let start = map
+                .lines()
+                .flat_map(|row| row.chars())
+                .position(|cell| cell == '^')
+                .unwrap();
+            assert_eq!(
+                generator.loop_obstacle(&obstacles, (start % 20, start / 20)),
+                None
+            );
+        }
+
+        // Dense maps without the option trap the guard at least once.
+        let looping = (0..20).any(|seed| {
+            let generator = GuardMap {
+                width: 20,
+                height: 15,
+                density: 0.3,
+                loop_free: false,
+            };
+            let map = generator.generate(seed);
+            let obstacles: Vec<bool> = map
+                .lines()
+                .flat_map(|row| row.chars())
+                .map(|cell| cell == '#')
+                .collect();
+            let start = map
+                .lines()
+                .flat_map(|row| row.chars())
+                .position(|cell| cell == '^')
+                .unwrap();
+            generator
+                .loop_obstacle(&obstacles, (start % 20, start / 20))
+                .is_some()
+        });
+        assert!(looping);
+    }
+}
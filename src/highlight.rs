@@ -0,0 +1,241 @@
+//! Grids of characters with the cells of matches highlighted, for the terminal and
+//! as HTML.
+//!
+//! Each cell is coloured by how many matches cover it, so overlapping matches stand out.
+
+use console::Style;
+use std::path::PathBuf;
+
+/// Colours for cells covered by one, two, three and more matches.
+const LEVELS: [Level; 4] = [
+    Level {
+        paint: Style::yellow,
+        css: "#e5c07b",
+    },
+    Level {
+        paint: Style::green,
+        css: "#98c379",
+    },
+    Level {
+        paint: Style::cyan,
+        css: "#56b6c2",
+    },
+    Level {
+        paint: Style::magenta,
+        css: "#c678dd",
+    },
+];
+/// Drawn for unmatched cells when only matched letters are shown.
+const HIDDEN: char = '.';
+
+/// What the runner should do with highlighted grids.
+#[derive(Debug, Default, Clone)]
+pub struct HighlightOptions {
+    /// Print the highlighted grids after the solutions.
+    pub print: bool,
+    /// Hide the letters that are not part of a match.
+    pub only_matched: bool,
+    /// Write the highlighted grids to this HTML file.
+    pub html: Option<PathBuf>,
+}
+
+impl HighlightOptions {
+    pub fn is_enabled(&self) -> bool {
+        self.print || self.html.is_some()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Highlight {
+    grid: Vec<Vec<char>>,
+    /// Number of matches covering each cell.
+    hits: Vec<Vec<usize>>,
+    only_matched: bool,
+}
+
+impl Highlight {
+    pub fn new(grid: &[Vec<char>]) -> Self {
+        Self {
+            grid: grid.to_vec(),
+            hits: grid.iter().map(|row| vec![0; row.len()]).collect(),
+            only_matched: false,
+        }
+    }
+
+    pub fn only_matched(mut self, only_matched: bool) -> Self {
+        self.only_matched = only_matched;
+        self
+    }
+
+    /// Marks the cells `(x, y)` of one match, cells outside of the grid are ignored.
+    pub fn mark(&mut self, cells: impl IntoIterator<Item = (usize, usize)>) {
+        for (x, y) in cells {
+            if let Some(hits) = self.hits.get_mut(y).and_then(|row| row.get_mut(x)) {
+                *hits += 1;
+            }
+        }
+    }
+
+    pub fn hits(&self, x: usize, y: usize) -> usize {
+        self.hits
+            .get(y)
+            .and_then(|row| row.get(x))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Number of cells that are part of at least one match.
+    pub fn matched_cells(&self) -> usize {
+        self.hits.iter().flatten().filter(|&&hits| hits > 0).count()
+    }
+
+    fn cells(&self) -> impl Iterator<Item = Vec<(char, usize)>> + '_ {
+        self.grid.iter().zip(&self.hits).map(|(row, hits)| {
+            row.iter()
+                .zip(hits)
+                .map(|(&letter, &hits)| match hits {
+                    0 if self.only_matched => (HIDDEN, 0),
+                    _ => (letter, hits),
+                })
+                .collect()
+        })
+    }
+
+    /// The grid with console styles, followed by a legend of the colours.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for row in self.cells() {
+            for (letter, hits) in row {
+                out.push_str(&style(hits).apply_to(letter).to_string());
+            }
+            out.push('\n');
+        }
+
+        for hits in 1..=LEVELS.len() {
+            let mut label = matches_label(hits);
+            if hits == LEVELS.len() {
+                label = label.replace(' ', "+ ");
+            }
+            out.push_str(&format!("{} {}  ", style(hits).apply_to('■'), label));
+        }
+        out.push('\n');
+        out
+    }
+
+    /// The grid as a `<pre>` block, matched cells are `<span>`s coloured inline.
+    pub fn to_html(&self) -> String {
+        let mut out = String::from("<pre class=\"grid\">");
+        for row in self.cells() {
+            for (letter, hits) in row {
+                match level(hits) {
+                    Some(level) => out.push_str(&format!(
+                        "<span style=\"color:{}\" title=\"{}\">{}</span>",
+                        level.css,
+                        matches_label(hits),
+                        escape(letter)
+                    )),
+                    None => out.push_str(&escape(letter)),
+                }
+            }
+            out.push('\n');
+        }
+        out.push_str("</pre>");
+        out
+    }
+}
+
+/// A standalone page with a heading and grid for each highlight.
+pub fn html_page(title: &str, sections: &[(&str, &Highlight)]) -> String {
+    let mut page = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <style>body {{ background: #282c34; color: #5c6370; font-family: monospace; }} \
+         h1, h2 {{ color: #abb2bf; }} span {{ font-weight: bold; }}</style>\n</head>\n<body>\n\
+         <h1>{}</h1>\n",
+        escape_str(title),
+        escape_str(title)
+    );
+    for (heading, highlight) in sections {
+        page.push_str(&format!(
+            "<h2>{}</h2>\n{}\n",
+            escape_str(heading),
+            highlight.to_html()
+        ));
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+struct Level {
+    paint: fn(Style) -> Style,
+    css: &'static str,
+}
+
+fn level(hits: usize) -> Option<&'static Level> {
+    match hits {
+        0 => None,
+        hits => Some(&LEVELS[hits.min(LEVELS.len()) - 1]),
+    }
+}
+
+fn style(hits: usize) -> Style {
+    match level(hits) {
+        Some(level) => (level.paint)(Style::new()).bold(),
+        None => Style::new().dim(),
+    }
+}
+
+fn matches_label(hits: usize) -> String {
+    match hits {
+        1 => "1 match".to_string(),
+        hits => format!("{} matches", hits),
+    }
+}
+
+fn escape(letter: char) -> String {
+    match letter {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '"' => "&quot;".to_string(),
+        letter => letter.to_string(),
+    }
+}
+
+fn escape_str(text: &str) -> String {
+    text.chars().map(escape).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn grid(text: &str) -> Vec<Vec<char>> {
+        text.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn overlapping_matches() {
+        let mut highlight = Highlight::new(&grid("XMAS\nA<&B"));
+        highlight.mark([(0, 0), (1, 0), (2, 0), (3, 0)]);
+        highlight.mark([(2, 0), (2, 1), (9, 9)]);
+
+        assert_eq!(highlight.hits(2, 0), 2);
+        assert_eq!(highlight.hits(0, 1), 0);
+        assert_eq!(highlight.matched_cells(), 5);
+
+        let html = highlight.to_html();
+        assert!(html.contains("<span style=\"color:#98c379\" title=\"2 matches\">A</span>"));
+        assert!(html.contains("<span style=\"color:#e5c07b\" title=\"1 match\">&amp;</span>"));
+        assert!(html.contains("\nA&lt;"));
+    }
+
+    #[test]
+    fn only_matched() {
+        let mut highlight = Highlight::new(&grid("XMAS\nABCD")).only_matched(true);
+        highlight.mark([(1, 0), (2, 1)]);
+
+        let rendered = console::strip_ansi_codes(&highlight.render()).to_string();
+        assert!(rendered.starts_with(".M..\n..C.\n"));
+        assert!(rendered.contains("4+ matches"));
+    }
+}
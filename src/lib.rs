@@ -1,7 +1,128 @@
+use color_eyre::eyre::eyre;
 use std::fmt::Display;
 use std::fs::File;
 use std::io::{self, BufRead};
 use std::path::Path;
+use std::sync::OnceLock;
+
+pub mod cli;
+pub mod collections;
+pub mod day01;
+pub mod day02;
+pub mod day03;
+pub mod day04;
+pub mod day05;
+pub mod day06;
+pub mod day07;
+pub mod day08;
+pub mod day09;
+pub mod day10;
+pub mod day11;
+pub mod day12;
+pub mod day13;
+pub mod day14;
+pub mod day15;
+pub mod day16;
+pub mod day17;
+pub mod day18;
+pub mod day19;
+pub mod day20;
+pub mod day21;
+pub mod day22;
+pub mod day23;
+pub mod day24;
+pub mod day25;
+#[cfg(feature = "export")]
+pub mod export;
+pub mod frame;
+pub mod fuzzing;
+pub mod generate;
+pub mod highlight;
+pub mod memo;
+pub mod stencil;
+#[cfg(feature = "viz")]
+pub mod viz;
+pub mod word_search;
+
+/// Options passed to the `aoc2024` runner that days can act on.
+#[derive(Debug, Default, Clone)]
+pub struct RunOptions {
+    /// Render simulations in the terminal, needs the `viz` feature.
+    pub visualize: bool,
+    /// Frame rate to start visualising at, the visualizer's default if `None`.
+    #[cfg(feature = "viz")]
+    pub fps: Option<u32>,
+    /// Write the recorded path of simulations to this file.
+    pub record: Option<std::path::PathBuf>,
+    /// Print why puzzle input was rejected, e.g. which rules an update breaks.
+    pub explain: bool,
+    /// Write the explanations as JSON to this file.
+    pub explain_json: Option<std::path::PathBuf>,
+    /// Write the rules of graph puzzles to this Graphviz DOT file.
+    pub dot: Option<std::path::PathBuf>,
+    /// Highlight this update, counted from 1, in the DOT file.
+    pub dot_update: Option<usize>,
+    /// Which page counts as the middle of an update with an even number of pages.
+    pub even_middle: day05::EvenMiddle,
+    /// Show which cells of a grid puzzle matched.
+    pub highlight: highlight::HighlightOptions,
+    /// Write simulations to an image file, needs the `export` feature.
+    #[cfg(feature = "export")]
+    pub export: Option<export::ExportOptions>,
+}
+
+/// Reads a day's input, prints the solutions of both parts and whatever the run options ask for.
+pub type Solution = fn() -> color_eyre::Result<()>;
+
+/// The solutions of all days, starting with day 1.
+pub const DAYS: [Solution; 25] = [
+    day01::main,
+    day02::main,
+    day03::main,
+    day04::main,
+    day05::main,
+    day06::main,
+    day07::main,
+    day08::main,
+    day09::main,
+    day10::main,
+    day11::main,
+    day12::main,
+    day13::main,
+    day14::main,
+    day15::main,
+    day16::main,
+    day17::main,
+    day18::main,
+    day19::main,
+    day20::main,
+    day21::main,
+    day22::main,
+    day23::main,
+    day24::main,
+    day25::main,
+];
+
+/// Runs the solution of a day, counted from 1, inside a `day` span.
+pub fn run_day(day: u8) -> color_eyre::Result<()> {
+    let solution = DAYS
+        .get(usize::from(day).wrapping_sub(1))
+        .ok_or_else(|| eyre!("There is no day {}", day))?;
+    let _span = tracing::info_span!("day", day).entered();
+    solution()
+}
+
+static RUN_OPTIONS: OnceLock<RunOptions> = OnceLock::new();
+
+pub fn set_run_options(options: RunOptions) {
+    RUN_OPTIONS
+        .set(options)
+        .expect("Run options have already been set");
+}
+
+pub fn run_options() -> &'static RunOptions {
+    RUN_OPTIONS.get_or_init(RunOptions::default)
+}
 
 pub fn count_occurrences<T: PartialEq>(list: &[T], eq: T) -> usize {
     list.iter().filter(|it| **it == eq).count()
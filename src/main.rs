@@ -1,38 +1,20 @@
 use color_eyre::Result;
 
-use clap::{Parser, Subcommand};
-
-mod day01;
-mod day02;
-mod day03;
-mod day04;
-mod day05;
-mod day06;
-mod day07;
-mod day08;
-mod day09;
-mod day10;
-mod day11;
-mod day12;
-mod day13;
-mod day14;
-mod day15;
-mod day16;
-mod day17;
-mod day18;
-mod day19;
-mod day20;
-mod day21;
-mod day22;
-mod day23;
-mod day24;
-mod day25;
+use aoc2024::cli::RunArgs;
+use aoc2024::generate::{self, Generator, Rng};
+use clap::{Args, Parser, Subcommand};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
 
 #[derive(Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// Show debug output, repeat for trace output
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
 }
 
 #[derive(Subcommand)]
@@ -43,77 +25,163 @@ enum Commands {
 
         #[arg(short, long)]
         all: bool,
+
+        #[command(flatten)]
+        run: RunArgs,
     },
+    /// Write a random puzzle input for a day
+    Generate(GenerateArgs),
+}
+
+#[derive(Args)]
+struct GenerateArgs {
+    /// Day whose input format to generate
+    #[arg(value_parser = clap::value_parser!(u8).range(1..=6))]
+    day: u8,
+
+    /// Seed of the random numbers, the same seed gives the same input
+    #[arg(long, default_value_t = 2024)]
+    seed: u64,
+
+    /// Write the input to a file instead of stdout
+    #[arg(short, long, value_name = "PATH")]
+    output: Option<std::path::PathBuf>,
+
+    /// Number of id pairs (day 1), reports (day 2) or updates (day 5)
+    #[arg(long)]
+    lines: Option<usize>,
+
+    /// Fewest levels per report (day 2) or pages per update (day 5)
+    #[arg(long)]
+    min_len: Option<usize>,
+
+    /// Most levels per report (day 2) or pages per update (day 5)
+    #[arg(long)]
+    max_len: Option<usize>,
+
+    /// Length of the corrupted memory (day 3)
+    #[arg(long)]
+    bytes: Option<u64>,
+
+    /// Width of the grid (days 4 and 6)
+    #[arg(long)]
+    width: Option<usize>,
+
+    /// Height of the grid (days 4 and 6)
+    #[arg(long)]
+    height: Option<usize>,
+
+    /// Number of distinct pages (day 5)
+    #[arg(long)]
+    pages: Option<usize>,
+
+    /// Chance of a rule between two pages (day 5) or of an obstacle on a cell (day 6)
+    #[arg(long)]
+    density: Option<f64>,
+
+    /// Make all rules follow one order, so they never form a cycle (day 5)
+    #[arg(long)]
+    acyclic: bool,
+
+    /// Remove obstacles until the guard walks off the map without looping (day 6)
+    #[arg(long)]
+    loop_free: bool,
+}
+
+impl GenerateArgs {
+    fn generator(&self) -> Box<dyn Generator> {
+        match self.day {
+            1 => {
+                let defaults = generate::IdLists::default();
+                Box::new(generate::IdLists {
+                    lines: self.lines.unwrap_or(defaults.lines),
+                    ..defaults
+                })
+            }
+            2 => {
+                let defaults = generate::Reports::default();
+                Box::new(generate::Reports {
+                    reports: self.lines.unwrap_or(defaults.reports),
+                    min_levels: self.min_len.unwrap_or(defaults.min_levels),
+                    max_levels: self.max_len.unwrap_or(defaults.max_levels),
+                    ..defaults
+                })
+            }
+            3 => {
+                let defaults = generate::CorruptedMemory::default();
+                Box::new(generate::CorruptedMemory {
+                    bytes: self.bytes.unwrap_or(defaults.bytes),
+                })
+            }
+            4 => {
+                let defaults = generate::LetterGrid::default();
+                Box::new(generate::LetterGrid {
+                    width: self.width.unwrap_or(defaults.width),
+                    height: self.height.unwrap_or(defaults.height),
+                    ..defaults
+                })
+            }
+            5 => {
+                let defaults = generate::PageRules::default();
+                Box::new(generate::PageRules {
+                    pages: self.pages.unwrap_or(defaults.pages),
+                    density: self.density.unwrap_or(defaults.density),
+                    updates: self.lines.unwrap_or(defaults.updates),
+                    min_update: self.min_len.unwrap_or(defaults.min_update),
+                    max_update: self.max_len.unwrap_or(defaults.max_update),
+                    acyclic: self.acyclic,
+                })
+            }
+            6 => {
+                let defaults = generate::GuardMap::default();
+                Box::new(generate::GuardMap {
+                    width: self.width.unwrap_or(defaults.width),
+                    height: self.height.unwrap_or(defaults.height),
+                    density: self.density.unwrap_or(defaults.density),
+                    loop_free: self.loop_free,
+                })
+            }
+            day => unreachable!("clap only accepts implemented days, got {}", day),
+        }
+    }
+
+    fn run(&self) -> Result<()> {
+        let generator = self.generator();
+        let mut rng = Rng::new(self.seed);
+        match &self.output {
+            Some(path) => {
+                let mut out = BufWriter::new(File::create(path)?);
+                generator.write(&mut rng, &mut out)?;
+                out.flush()?;
+            }
+            None => {
+                let mut out = BufWriter::new(io::stdout().lock());
+                generator.write(&mut rng, &mut out)?;
+                out.flush()?;
+            }
+        }
+        Ok(())
+    }
 }
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    aoc2024::cli::init(cli.verbose)?;
 
     match &cli.command {
-        Commands::Day { day, .. } => match day {
-            Some(1) => DAYS[0](),
-            Some(2) => DAYS[1](),
-            Some(3) => DAYS[2](),
-            Some(4) => DAYS[3](),
-            Some(5) => DAYS[4](),
-            Some(6) => DAYS[5](),
-            Some(7) => DAYS[6](),
-            Some(8) => DAYS[7](),
-            Some(9) => DAYS[8](),
-            Some(10) => DAYS[9](),
-            Some(11) => DAYS[10](),
-            Some(12) => DAYS[11](),
-            Some(13) => DAYS[12](),
-            Some(14) => DAYS[13](),
-            Some(15) => DAYS[14](),
-            Some(16) => DAYS[15](),
-            Some(17) => DAYS[16](),
-            Some(18) => DAYS[17](),
-            Some(19) => DAYS[18](),
-            Some(20) => DAYS[19](),
-            Some(21) => DAYS[20](),
-            Some(22) => DAYS[21](),
-            Some(23) => DAYS[22](),
-            Some(24) => DAYS[23](),
-            Some(25) => DAYS[24](),
-            None => {
-                for exec in DAYS {
-                    exec()?;
-                    println!()
+        Commands::Day { day, run, .. } => {
+            aoc2024::set_run_options(run.options());
+            match day {
+                Some(day) => aoc2024::run_day(*day),
+                None => {
+                    for day in 1..=aoc2024::DAYS.len() as u8 {
+                        aoc2024::run_day(day)?;
+                        println!()
+                    }
+                    Ok(())
                 }
-                Ok(())
             }
-            _ => panic!("You're evil"),
-        },
+        }
+        Commands::Generate(generate) => generate.run(),
     }
 }
-
-type MainFunction = fn() -> Result<()>;
-
-const DAYS: [MainFunction; 25] = [
-    day01::main,
-    day02::main,
-    day03::main,
-    day04::main,
-    day05::main,
-    day06::main,
-    day07::main,
-    day08::main,
-    day09::main,
-    day10::main,
-    day11::main,
-    day12::main,
-    day13::main,
-    day14::main,
-    day15::main,
-    day16::main,
-    day17::main,
-    day18::main,
-    day19::main,
-    day20::main,
-    day21::main,
-    day22::main,
-    day23::main,
-    day24::main,
-    day25::main,
-];
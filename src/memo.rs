@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+
+use tracing::debug;
+
+/// Cache for the results of a pure (recursive) function, keyed by its arguments.
+///
+/// The computation is handed a mutable reference to the cache itself, so recursive
+/// calls go through the same cache:
+///
+/// ```
+/// use aoc2024::memo::Memo;
+///
+/// fn fib(memo: &mut Memo<u64, u64>, n: u64) -> u64 {
+///     memo.get_or_compute(n, |memo, &n| match n {
+///         0 | 1 => n,
+///         n => fib(memo, n - 1) + fib(memo, n - 2),
+///     })
+/// }
+///
+/// let mut memo = Memo::new();
+/// assert_eq!(fib(&mut memo, 90), 2880067194370816120);
+/// ```
+///
+/// Keep the same `Memo` around to share results between part 1 and part 2,
+/// or call [`Memo::clear`] in between if the parts must not see each other's results.
+#[derive(Debug, Clone)]
+pub struct Memo<K, V> {
+    cache: HashMap<K, V>,
+    stats: MemoStats,
+}
+
+/// Hit/miss counters of a [`Memo`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MemoStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl<K, V> Memo<K, V>
+where
+    K: Hash + Eq,
+    V: Clone,
+{
+    pub fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+            stats: MemoStats::default(),
+        }
+    }
+
+    /// Returns the cached value for `key`, or computes it with `compute` and caches it.
+    pub fn get_or_compute<F>(&mut self, key: K, compute: F) -> V
+    where
+        F: FnOnce(&mut Self, &K) -> V,
+    {
+        if let Some(value) = self.cache.get(&key) {
+            self.stats.hits += 1;
+            return value.clone();
+        }
+
+        self.stats.misses += 1;
+        let value = compute(self, &key);
+        self.cache.insert(key, value.clone());
+        value
+    }
+
+    pub fn get(&self, key: &K) -> Option<&V> {
+        self.cache.get(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+
+    /// Drops all cached values and resets the statistics.
+    pub fn clear(&mut self) {
+        self.cache.clear();
+        self.stats = MemoStats::default();
+    }
+
+    pub fn stats(&self) -> MemoStats {
+        self.stats
+    }
+
+    /// Logs the hit/miss statistics at debug level.
+    pub fn debug_stats(&self, name: &str) {
+        debug!("Memo {}: {}, {} entries", name, self.stats, self.len());
+    }
+}
+
+impl<K, V> Default for Memo<K, V>
+where
+    K: Hash + Eq,
+    V: Clone,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoStats {
+    pub fn lookups(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups() == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.lookups() as f64
+    }
+}
+
+impl fmt::Display for MemoStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} hits, {} misses ({:.1}% hit rate)",
+            self.hits,
+            self.misses,
+            self.hit_rate() * 100.0
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn count_stones(memo: &mut Memo<(u64, u32), u64>, stone: u64, blinks: u32) -> u64 {
+        memo.get_or_compute((stone, blinks), |memo, &(stone, blinks)| {
+            if blinks == 0 {
+                return 1;
+            }
+            let digits = stone.checked_ilog10().unwrap_or(0) + 1;
+            if stone == 0 {
+                count_stones(memo, 1, blinks - 1)
+            } else if digits % 2 == 0 {
+                let split = 10u64.pow(digits / 2);
+                count_stones(memo, stone / split, blinks - 1)
+                    + count_stones(memo, stone % split, blinks - 1)
+            } else {
+                count_stones(memo, stone * 2024, blinks - 1)
+            }
+        })
+    }
+
+    #[test]
+    fn recursive_results() {
+        let mut memo = Memo::new();
+        let stones: u64 = [125, 17]
+            .iter()
+            .map(|&stone| count_stones(&mut memo, stone, 25))
+            .sum();
+        assert_eq!(stones, 55312);
+    }
+
+    #[test]
+    fn shared_between_parts() {
+        let mut memo = Memo::new();
+        count_stones(&mut memo, 125, 6);
+        let misses = memo.stats().misses;
+
+        count_stones(&mut memo, 125, 6);
+        assert_eq!(memo.stats().misses, misses);
+        assert!(memo.stats().hits > 0);
+    }
+
+    #[test]
+    fn clear_resets() {
+        let mut memo = Memo::new();
+        count_stones(&mut memo, 17, 6);
+        memo.clear();
+
+        assert!(memo.is_empty());
+        assert_eq!(memo.stats(), MemoStats::default());
+    }
+}
@@ -0,0 +1,311 @@
+//! Matching small 2D patterns against grids of characters.
+//!
+//! A [`Stencil`] is written row by row, `.` matches any character:
+//!
+//! ```
+//! use aoc2024::stencil::{Stencil, StencilMatcher};
+//!
+//! let grid: Vec<Vec<char>> = [".M.S", "..A.", ".M.S"]
+//!     .iter()
+//!     .map(|row| row.chars().collect())
+//!     .collect();
+//!
+//! let x_mas: Stencil = "M.S\n.A.\nM.S".parse().unwrap();
+//! let placements = StencilMatcher::new(x_mas).rotations(true).find(&grid);
+//! assert_eq!(placements.len(), 1);
+//! assert_eq!(placements[0].position, (1, 0));
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+
+pub const WILDCARD: char = '.';
+
+/// A rectangular pattern, `None` cells match anything.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Stencil {
+    width: usize,
+    height: usize,
+    cells: Vec<Option<char>>,
+}
+
+impl Stencil {
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<char> {
+        self.cells[y * self.width + x]
+    }
+
+    /// Turned by 90 degrees clockwise.
+    pub fn rotated(&self) -> Self {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.width {
+            for x in 0..self.height {
+                cells.push(self.get(y, self.height - 1 - x));
+            }
+        }
+
+        Self {
+            width: self.height,
+            height: self.width,
+            cells,
+        }
+    }
+
+    /// Mirrored left to right.
+    pub fn reflected(&self) -> Self {
+        let mut cells = Vec::with_capacity(self.cells.len());
+        for y in 0..self.height {
+            for x in (0..self.width).rev() {
+                cells.push(self.get(x, y));
+            }
+        }
+
+        Self {
+            width: self.width,
+            height: self.height,
+            cells,
+        }
+    }
+
+    /// Whether the stencil placed with its top left corner at `(x, y)` fits the grid.
+    pub fn matches_at(&self, grid: &[Vec<char>], (x, y): (usize, usize)) -> bool {
+        (0..self.height).all(|dy| {
+            let Some(row) = grid.get(y + dy) else {
+                return false;
+            };
+            (0..self.width).all(|dx| match (self.get(dx, dy), row.get(x + dx)) {
+                (_, None) => false,
+                (None, Some(_)) => true,
+                (Some(expected), Some(&found)) => expected == found,
+            })
+        })
+    }
+}
+
+impl FromStr for Stencil {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let rows: Vec<&str> = pattern.lines().map(str::trim).collect();
+        let width = rows.first().map_or(0, |row| row.chars().count());
+        if width == 0 {
+            return Err("A stencil needs at least one cell".to_string());
+        }
+        if rows.iter().any(|row| row.chars().count() != width) {
+            return Err("All rows of a stencil need the same length".to_string());
+        }
+
+        let cells = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|char| (char != WILDCARD).then_some(char))
+            .collect();
+        Ok(Self {
+            width,
+            height: rows.len(),
+            cells,
+        })
+    }
+}
+
+impl fmt::Display for Stencil {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..self.height {
+            if y > 0 {
+                writeln!(f)?;
+            }
+            for x in 0..self.width {
+                write!(f, "{}", self.get(x, y).unwrap_or(WILDCARD))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Where a stencil matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Placement {
+    /// Top left corner `(x, y)` in the grid.
+    pub position: (usize, usize),
+    /// Index into [`StencilMatcher::variants`].
+    pub variant: usize,
+}
+
+/// Finds every placement of a stencil, optionally turned or mirrored.
+pub struct StencilMatcher {
+    stencil: Stencil,
+    rotations: bool,
+    reflections: bool,
+    variants: Vec<Stencil>,
+}
+
+impl StencilMatcher {
+    pub fn new(stencil: Stencil) -> Self {
+        Self {
+            variants: vec![stencil.clone()],
+            stencil,
+            rotations: false,
+            reflections: false,
+        }
+    }
+
+    /// Also match the stencil turned by 90, 180 and 270 degrees.
+    pub fn rotations(mut self, rotations: bool) -> Self {
+        self.rotations = rotations;
+        self.update_variants();
+        self
+    }
+
+    /// Also match the stencil mirrored, and with rotations its mirrored turns.
+    pub fn reflections(mut self, reflections: bool) -> Self {
+        self.reflections = reflections;
+        self.update_variants();
+        self
+    }
+
+    /// The distinct stencils that are matched, a symmetric stencil has fewer than eight.
+    pub fn variants(&self) -> &[Stencil] {
+        &self.variants
+    }
+
+    fn update_variants(&mut self) {
+        let mut bases = vec![self.stencil.clone()];
+        if self.reflections {
+            bases.push(self.stencil.reflected());
+        }
+
+        self.variants.clear();
+        for base in bases {
+            let mut stencil = base;
+            let turns = if self.rotations { 4 } else { 1 };
+            for _ in 0..turns {
+                if !self.variants.contains(&stencil) {
+                    self.variants.push(stencil.clone());
+                }
+                stencil = stencil.rotated();
+            }
+        }
+    }
+
+    /// Every placement of every variant, sorted by position.
+    pub fn find(&self, grid: &[Vec<char>]) -> Vec<Placement> {
+        let height = grid.len();
+        let width = grid.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut placements = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                for (variant, stencil) in self.variants.iter().enumerate() {
+                    if stencil.matches_at(grid, (x, y)) {
+                        placements.push(Placement {
+                            position: (x, y),
+                            variant,
+                        });
+                    }
+                }
+            }
+        }
+
+        placements
+    }
+
+    pub fn count(&self, grid: &[Vec<char>]) -> usize {
+        self.find(grid).len()
+    }
+
+    /// Grid positions of the cells a placement matched, wildcards left out.
+    pub fn cells(&self, placement: &Placement) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let stencil = &self.variants[placement.variant];
+        let (x, y) = placement.position;
+        (0..stencil.height)
+            .flat_map(move |dy| (0..stencil.width).map(move |dx| (dx, dy)))
+            .filter(|&(dx, dy)| stencil.get(dx, dy).is_some())
+            .map(move |(dx, dy)| (x + dx, y + dy))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
+
+    fn grid(text: &str) -> Vec<Vec<char>> {
+        text.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn transformations() {
+        let stencil: Stencil = "AB.\nCDE".parse().unwrap();
+        assert_eq!(stencil.rotated().to_string(), "CA\nDB\nE.");
+        assert_eq!(stencil.reflected().to_string(), ".BA\nEDC");
+        assert_eq!(stencil.rotated().rotated().rotated().rotated(), stencil);
+
+        assert!("AB\nC".parse::<Stencil>().is_err());
+        assert!("".parse::<Stencil>().is_err());
+    }
+
+    #[test]
+    fn variants() {
+        let x_mas: Stencil = "M.S\n.A.\nM.S".parse().unwrap();
+        assert_eq!(StencilMatcher::new(x_mas.clone()).variants().len(), 1);
+        assert_eq!(
+            StencilMatcher::new(x_mas.clone())
+                .rotations(true)
+                .variants()
+                .len(),
+            4
+        );
+        assert_eq!(
+            StencilMatcher::new(x_mas)
+                .rotations(true)
+                .reflections(true)
+                .variants()
+                .len(),
+            4
+        );
+
+        let corner: Stencil = "AB\nC.".parse().unwrap();
+        let matcher = StencilMatcher::new(corner)
+            .rotations(true)
+            .reflections(true);
+        assert_eq!(matcher.variants().len(), 8);
+    }
+
+    #[test]
+    fn example() {
+        let x_mas: Stencil = "M.S\n.A.\nM.S".parse().unwrap();
+        let matcher = StencilMatcher::new(x_mas).rotations(true);
+        assert_eq!(matcher.count(&grid(EXAMPLE)), 9);
+
+        let placements = matcher.find(&grid(EXAMPLE));
+        assert_eq!(
+            placements[0],
+            Placement {
+                position: (1, 0),
+                variant: 0,
+            }
+        );
+        assert_eq!(
+            matcher.cells(&placements[0]).collect::<Vec<_>>(),
+            vec![(1, 0), (3, 0), (2, 1), (1, 2), (3, 2)]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Terminal visualisation for grid simulations, enabled with the `viz` feature.
+//!
+//! A simulation describes its cell kinds in a [`Legend`], renders its state into a
+//! [`Frame`] and hands the frame to a [`Visualizer`], which redraws the terminal and
+//! paces the simulation:
+//!
+//! | key           | action                    |
+//! |---------------|---------------------------|
+//! | `space`       | pause / resume            |
+//! | `n`, `→`      | step one frame (paused)   |
+//! | `+` / `-`     | double / halve frame rate |
+//! | `q`, `esc`    | stop visualising          |
+
+use crate::frame::{Frame, Legend};
+use console::{Style, Term};
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_FPS: u32 = 20;
+pub const MAX_FPS: u32 = 960;
+
+/// Draws frames to the terminal at a fixed frame rate and handles the control keys.
+///
+/// The terminal is in raw mode while the visualizer is alive.
+pub struct Visualizer {
+    term: Term,
+    _raw: RawMode,
+    title: String,
+    legend: Legend,
+    fps: u32,
+    paused: bool,
+    stopped: bool,
+    frame_count: usize,
+}
+
+impl Visualizer {
+    pub fn new(title: &str, legend: Legend) -> io::Result<Self> {
+        let term = Term::stdout();
+        let raw = RawMode::enable(&term)?;
+        term.hide_cursor()?;
+        term.clear_screen()?;
+
+        Ok(Self {
+            term,
+            _raw: raw,
+            title: title.to_string(),
+            legend,
+            fps: DEFAULT_FPS,
+            paused: false,
+            stopped: false,
+            frame_count: 0,
+        })
+    }
+
+    pub fn with_fps(mut self, fps: u32) -> Self {
+        self.fps = fps.clamp(1, MAX_FPS);
+        self
+    }
+
+    /// Whether the user quit the visualisation; further frames are ignored.
+    pub fn is_stopped(&self) -> bool {
+        self.stopped
+    }
+
+    /// Draws the frame, then blocks until the next frame is due.
+    pub fn show(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.stopped {
+            return Ok(());
+        }
+
+        self.frame_count += 1;
+        self.draw(frame)?;
+        self.wait()
+    }
+
+    fn draw(&self, frame: &Frame) -> io::Result<()> {
+        let mut screen = String::new();
+        screen.push_str(&format!(
+            "{}\r\n\r\n",
+            Style::new().bold().apply_to(&self.title)
+        ));
+
+        for row in frame.rows() {
+            screen.push(' ');
+            for cell in row {
+                match self.legend.get(cell.kind) {
+                    Some(entry) => {
+                        let glyph = cell.glyph.unwrap_or(entry.glyph);
+                        screen.push_str(&entry.style.apply_to(glyph).to_string());
+                    }
+                    None => screen.push(cell.glyph.unwrap_or('?')),
+                }
+            }
+            screen.push_str("\r\n");
+        }
+
+        screen.push_str("\r\n");
+        for entry in self.legend.entries() {
+            screen.push_str(&format!(
+                " {} {} ",
+                entry.style.apply_to(entry.glyph),
+                entry.label
+            ));
+        }
+        screen.push_str("\r\n");
+
+        let state = if self.paused { "paused" } else { "running" };
+        screen.push_str(&format!(
+            "{}\r\n",
+            Style::new().dim().apply_to(format!(
+                " frame {} | {} fps | {} | space: pause  n: step  +/-: speed  q: quit",
+                self.frame_count, self.fps, state
+            ))
+        ));
+
+        self.term.move_cursor_to(0, 0)?;
+        let mut out = io::stdout().lock();
+        out.write_all(screen.as_bytes())?;
+        out.flush()
+    }
+
+    fn wait(&mut self) -> io::Result<()> {
+        let due = Instant::now() + Duration::from_secs(1) / self.fps;
+
+        loop {
+            let timeout = if self.paused {
+                Duration::from_millis(100)
+            } else {
+                due.saturating_duration_since(Instant::now())
+            };
+
+            if !event::poll(timeout)? {
+                if self.paused {
+                    continue;
+                }
+                return Ok(());
+            }
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match key {
+                KeyEvent {
+                    code: KeyCode::Char('c'),
+                    modifiers: KeyModifiers::CONTROL,
+                    ..
+                }
+                | KeyEvent {
+                    code: KeyCode::Char('q') | KeyCode::Esc,
+                    ..
+                } => {
+                    self.stopped = true;
+                    return Ok(());
+                }
+                KeyEvent {
+                    code: KeyCode::Char(' '),
+                    ..
+                } => self.paused = !self.paused,
+                KeyEvent {
+                    code: KeyCode::Char('n') | KeyCode::Right,
+                    ..
+                } if self.paused => return Ok(()),
+                KeyEvent {
+                    code: KeyCode::Char('+'),
+                    ..
+                } => self.fps = (self.fps * 2).min(MAX_FPS),
+                KeyEvent {
+                    code: KeyCode::Char('-'),
+                    ..
+                } => self.fps = (self.fps / 2).max(1),
+                _ => (),
+            }
+        }
+    }
+}
+
+/// Keeps the terminal in raw mode and restores it, cursor included, when dropped, also
+/// when setting up the visualizer fails halfway.
+struct RawMode {
+    term: Term,
+}
+
+impl RawMode {
+    fn enable(term: &Term) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        Ok(Self { term: term.clone() })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = self.term.show_cursor();
+        let _ = terminal::disable_raw_mode();
+    }
+}
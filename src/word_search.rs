@@ -0,0 +1,387 @@
+//! Word search over grids of characters.
+//!
+//! The grid is cut into lines along every allowed direction and all words are looked
+//! for at once with an Aho-Corasick automaton, so the cost hardly grows with the
+//! number of words.
+//!
+//! ```
+//! use aoc2024::word_search::{Direction, WordSearch};
+//!
+//! let grid: Vec<Vec<char>> = ["XMAS", "MAAX", "AXAS", "SMXS"]
+//!     .iter()
+//!     .map(|row| row.chars().collect())
+//!     .collect();
+//!
+//! let matches = WordSearch::new(["XMAS"]).find(&grid);
+//! assert_eq!(matches.len(), 2);
+//! assert_eq!(matches[0].start, (0, 0));
+//! assert_eq!(matches[0].direction, Direction::Right);
+//! ```
+
+use aho_corasick::AhoCorasick;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Direction {
+    Right,
+    DownRight,
+    Down,
+    DownLeft,
+    Left,
+    UpLeft,
+    Up,
+    UpRight,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 8] = [
+        Direction::Right,
+        Direction::DownRight,
+        Direction::Down,
+        Direction::DownLeft,
+        Direction::Left,
+        Direction::UpLeft,
+        Direction::Up,
+        Direction::UpRight,
+    ];
+
+    /// Change of x and y for one step.
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Direction::Right => (1, 0),
+            Direction::DownRight => (1, 1),
+            Direction::Down => (0, 1),
+            Direction::DownLeft => (-1, 1),
+            Direction::Left => (-1, 0),
+            Direction::UpLeft => (-1, -1),
+            Direction::Up => (0, -1),
+            Direction::UpRight => (1, -1),
+        }
+    }
+
+    pub fn is_diagonal(&self) -> bool {
+        let (dx, dy) = self.offset();
+        dx != 0 && dy != 0
+    }
+
+    /// Whether the direction reads against the usual left to right, top to bottom.
+    pub fn is_reverse(&self) -> bool {
+        matches!(
+            self,
+            Direction::Left | Direction::UpLeft | Direction::Up | Direction::UpRight
+        )
+    }
+}
+
+/// A word found in the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Match {
+    /// Index of the word in the list the search was created with.
+    pub word: usize,
+    /// Position `(x, y)` of the first letter.
+    pub start: (usize, usize),
+    pub direction: Direction,
+    /// Number of letters.
+    pub len: usize,
+}
+
+impl Match {
+    /// Positions of the letters in order, wrapped around a grid of the given size.
+    pub fn cells(&self, width: usize, height: usize) -> impl Iterator<Item = (usize, usize)> {
+        let (dx, dy) = self.direction.offset();
+        let (x, y) = (self.start.0 as isize, self.start.1 as isize);
+        (0..self.len as isize).map(move |i| {
+            (
+                (x + dx * i).rem_euclid(width as isize) as usize,
+                (y + dy * i).rem_euclid(height as isize) as usize,
+            )
+        })
+    }
+}
+
+/// Finds a list of words in a rectangular grid of characters.
+///
+/// By default words are looked for in all eight directions without wrapping around
+/// the edges, like in day 4.
+pub struct WordSearch {
+    words: Vec<String>,
+    automaton: AhoCorasick,
+    /// Letters of the longest word.
+    longest: usize,
+    straight: bool,
+    diagonal: bool,
+    reverse: bool,
+    wrap_around: bool,
+}
+
+impl WordSearch {
+    pub fn new<I, S>(words: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let words: Vec<String> = words.into_iter().map(Into::into).collect();
+        assert!(
+            words.iter().all(|word| !word.is_empty()),
+            "Can't search for empty words"
+        );
+
+        let automaton = AhoCorasick::new(&words).expect("automaton for the word list");
+        let longest = words
+            .iter()
+            .map(|word| word.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            words,
+            automaton,
+            longest,
+            straight: true,
+            diagonal: true,
+            reverse: true,
+            wrap_around: false,
+        }
+    }
+
+    /// Search horizontally and vertically.
+    pub fn straight(mut self, straight: bool) -> Self {
+        self.straight = straight;
+        self
+    }
+
+    /// Search along both diagonals.
+    pub fn diagonal(mut self, diagonal: bool) -> Self {
+        self.diagonal = diagonal;
+        self
+    }
+
+    /// Also search right to left and bottom to top.
+    pub fn reverse(mut self, reverse: bool) -> Self {
+        self.reverse = reverse;
+        self
+    }
+
+    /// Let words continue on the opposite edge of the grid.
+    pub fn wrap_around(mut self, wrap_around: bool) -> Self {
+        self.wrap_around = wrap_around;
+        self
+    }
+
+    pub fn words(&self) -> &[String] {
+        &self.words
+    }
+
+    pub fn word(&self, found: &Match) -> &str {
+        &self.words[found.word]
+    }
+
+    pub fn directions(&self) -> impl Iterator<Item = Direction> + '_ {
+        Direction::ALL.into_iter().filter(|direction| {
+            let kind = if direction.is_diagonal() {
+                self.diagonal
+            } else {
+                self.straight
+            };
+            kind && (self.reverse || !direction.is_reverse())
+        })
+    }
+
+    /// Every occurrence of every word, overlapping ones included, sorted by position.
+    pub fn find(&self, grid: &[Vec<char>]) -> Vec<Match> {
+        let mut matches = vec![];
+        for direction in self.directions() {
+            for line in self.lines(grid, direction) {
+                self.find_in_line(&line, direction, &mut matches);
+            }
+        }
+
+        matches.sort_by_key(|found| (found.start.1, found.start.0, found.direction, found.word));
+        matches
+    }
+
+    pub fn count(&self, grid: &[Vec<char>]) -> usize {
+        self.find(grid).len()
+    }
+
+    fn find_in_line(&self, line: &Line, direction: Direction, matches: &mut Vec<Match>) {
+        let mut text = String::with_capacity(line.letters.len());
+        let mut char_starts = Vec::with_capacity(line.letters.len());
+        for &(letter, _) in &line.letters {
+            char_starts.push(text.len());
+            text.push(letter);
+        }
+
+        for found in self.automaton.find_overlapping_iter(&text) {
+            let Ok(index) = char_starts.binary_search(&found.start()) else {
+                continue;
+            };
+            if index >= line.starts {
+                continue;
+            }
+
+            let word = found.pattern().as_usize();
+            matches.push(Match {
+                word,
+                start: line.letters[index].1,
+                direction,
+                len: self.words[word].chars().count(),
+            });
+        }
+    }
+
+    /// Cuts the grid into lines along the direction. When wrapping around, every line
+    /// is a cycle, repeated far enough for the longest word to fit behind each start.
+    fn lines(&self, grid: &[Vec<char>], direction: Direction) -> Vec<Line> {
+        let height = grid.len() as isize;
+        let width = grid.first().map_or(0, Vec::len) as isize;
+        let (dx, dy) = direction.offset();
+        let letter = |x: isize, y: isize| grid[y as usize][x as usize];
+        let in_bounds = |x: isize, y: isize| x >= 0 && x < width && y >= 0 && y < height;
+
+        let mut lines = vec![];
+        if self.wrap_around {
+            let mut seen = vec![false; (width * height) as usize];
+            for y in 0..height {
+                for x in 0..width {
+                    if seen[(y * width + x) as usize] {
+                        continue;
+                    }
+
+                    let mut cycle = vec![];
+                    let (mut cx, mut cy) = (x, y);
+                    while !seen[(cy * width + cx) as usize] {
+                        seen[(cy * width + cx) as usize] = true;
+                        cycle.push((letter(cx, cy), (cx as usize, cy as usize)));
+                        cx = (cx + dx).rem_euclid(width);
+                        cy = (cy + dy).rem_euclid(height);
+                    }
+
+                    let starts = cycle.len();
+                    let letters = (0..starts + self.longest.saturating_sub(1))
+                        .map(|i| cycle[i % starts])
+                        .collect();
+                    lines.push(Line { letters, starts });
+                }
+            }
+        } else {
+            for y in 0..height {
+                for x in 0..width {
+                    if in_bounds(x - dx, y - dy) {
+                        continue;
+                    }
+
+                    let mut letters = vec![];
+                    let (mut cx, mut cy) = (x, y);
+                    while in_bounds(cx, cy) {
+                        letters.push((letter(cx, cy), (cx as usize, cy as usize)));
+                        cx += dx;
+                        cy += dy;
+                    }
+
+                    let starts = letters.len();
+                    lines.push(Line { letters, starts });
+                }
+            }
+        }
+
+        lines
+    }
+}
+
+/// Letters along a direction with their positions. Only the first `starts` letters
+/// may begin a match, the rest is repeated from the start when wrapping around.
+struct Line {
+    letters: Vec<(char, (usize, usize))>,
+    starts: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "\
+MMMSXXMASM
+MSAMXMSMSA
+AMXSXMAAMM
+MSAMASMSMX
+XMASAMXAMM
+XXAMMXXAMA
+SMSMSASXSS
+SAXAMASAAA
+MAMMMXMMMM
+MXMXAXMASX";
+
+    fn grid(text: &str) -> Vec<Vec<char>> {
+        text.lines().map(|line| line.chars().collect()).collect()
+    }
+
+    #[test]
+    fn example() {
+        let matches = WordSearch::new(["XMAS"]).find(&grid(EXAMPLE));
+        assert_eq!(matches.len(), 18);
+        assert!(matches.contains(&Match {
+            word: 0,
+            start: (5, 0),
+            direction: Direction::Right,
+            len: 4,
+        }));
+    }
+
+    #[test]
+    fn directions() {
+        let grid = grid(EXAMPLE);
+        let count = |search: WordSearch| search.count(&grid);
+
+        let forward = count(WordSearch::new(["XMAS"]).reverse(false));
+        let backward = count(WordSearch::new(["SAMX"]).reverse(false));
+        assert_eq!(forward + backward, 18);
+
+        let straight = count(WordSearch::new(["XMAS"]).diagonal(false));
+        let diagonal = count(WordSearch::new(["XMAS"]).straight(false));
+        assert_eq!(straight + diagonal, 18);
+        assert_eq!(diagonal, 10);
+    }
+
+    #[test]
+    fn several_words() {
+        let search = WordSearch::new(["XMAS", "MAS", "AM"]);
+        let matches = search.find(&grid(EXAMPLE));
+
+        let count = |word: &str| {
+            matches
+                .iter()
+                .filter(|found| search.word(found) == word)
+                .count()
+        };
+        assert_eq!(count("XMAS"), 18);
+        assert_eq!(count("MAS"), WordSearch::new(["MAS"]).count(&grid(EXAMPLE)));
+        assert!(count("AM") > 0);
+    }
+
+    #[test]
+    fn wrap_around() {
+        let grid = grid("ASXM\nBBBB\nBBBB");
+        assert_eq!(WordSearch::new(["XMAS"]).count(&grid), 0);
+
+        let matches = WordSearch::new(["XMAS"]).wrap_around(true).find(&grid);
+        assert_eq!(
+            matches,
+            vec![Match {
+                word: 0,
+                start: (2, 0),
+                direction: Direction::Right,
+                len: 4,
+            }]
+        );
+        assert_eq!(
+            matches[0].cells(4, 3).collect::<Vec<_>>(),
+            vec![(2, 0), (3, 0), (0, 0), (1, 0)]
+        );
+
+        // Diagonals of a non-square grid visit every cell before repeating.
+        let grid = self::grid("XBB\nBMB\nBBA\nSBB");
+        let matches = WordSearch::new(["XMAS"]).wrap_around(true).find(&grid);
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].direction, Direction::DownRight);
+    }
+}